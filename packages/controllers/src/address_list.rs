@@ -0,0 +1,441 @@
+use schemars::JsonSchema;
+use std::fmt;
+use thiserror::Error;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, Addr, BlockInfo, CustomQuery, Deps, DepsMut, MessageInfo, Order, Response, StdError,
+    StdResult, Storage,
+};
+use cw_storage_plus::{Bound, Map, Namespace};
+use cw_utils::{calc_range_start, Expiration};
+
+use crate::admin::{Admin, AdminError};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+/// Defines how the addresses stored in an `AddressList` are interpreted
+#[cw_serde]
+#[derive(Copy)]
+pub enum AddressListMode {
+    /// Only listed (and not expired) addresses are allowed
+    Allow,
+    /// Listed (and not expired) addresses are blocked, everyone else is allowed
+    Deny,
+}
+
+#[cw_serde]
+pub struct AddressListEntry {
+    pub address: String,
+    pub expires: Expiration,
+}
+
+#[cw_serde]
+pub struct AddressListResponse {
+    pub entries: Vec<AddressListEntry>,
+}
+
+#[derive(Error, Debug)]
+pub enum AddressListError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Admin(#[from] AdminError),
+
+    #[error("Address {addr} is not allowed")]
+    NotAllowed { addr: String },
+
+    #[error("Address {addr} is not listed")]
+    NotListed { addr: String },
+}
+
+// each address is stored on its own key, so the list can grow large and be paginated
+pub struct AddressList {
+    mode: AddressListMode,
+    entries: Map<&'static Addr, Expiration>,
+}
+
+impl AddressList {
+    pub const fn new(storage_key: &'static str, mode: AddressListMode) -> Self {
+        AddressList {
+            mode,
+            entries: Map::new(storage_key),
+        }
+    }
+
+    pub fn new_dyn(storage_key: impl Into<Namespace>, mode: AddressListMode) -> Self {
+        AddressList {
+            mode,
+            entries: Map::new_dyn(storage_key),
+        }
+    }
+
+    pub fn mode(&self) -> AddressListMode {
+        self.mode
+    }
+
+    /// Adds all given addresses to the list, valid until `expires`.
+    /// Adding an address that is already listed updates its expiration.
+    pub fn add_addresses(
+        &self,
+        storage: &mut dyn Storage,
+        addrs: &[Addr],
+        expires: Expiration,
+    ) -> StdResult<()> {
+        for addr in addrs {
+            self.entries.save(storage, addr, &expires)?;
+        }
+        Ok(())
+    }
+
+    /// Removes all given addresses from the list.
+    /// Fails without removing anything if one of them is not listed.
+    pub fn remove_addresses(
+        &self,
+        storage: &mut dyn Storage,
+        addrs: &[Addr],
+    ) -> Result<(), AddressListError> {
+        if let Some(missing) = addrs.iter().find(|a| !self.entries.has(storage, a)) {
+            return Err(AddressListError::NotListed {
+                addr: missing.to_string(),
+            });
+        }
+        for addr in addrs {
+            self.entries.remove(storage, addr);
+        }
+        Ok(())
+    }
+
+    /// Returns true if the address is on the list and the entry did not expire yet
+    pub fn is_listed(
+        &self,
+        storage: &dyn Storage,
+        addr: &Addr,
+        block: &BlockInfo,
+    ) -> StdResult<bool> {
+        Ok(self
+            .entries
+            .may_load(storage, addr)?
+            .map(|expires| !expires.is_expired(block))
+            .unwrap_or(false))
+    }
+
+    /// Returns true if the address may act, taking the list mode into account
+    pub fn is_allowed(
+        &self,
+        storage: &dyn Storage,
+        addr: &Addr,
+        block: &BlockInfo,
+    ) -> StdResult<bool> {
+        let listed = self.is_listed(storage, addr, block)?;
+        Ok(match self.mode {
+            AddressListMode::Allow => listed,
+            AddressListMode::Deny => !listed,
+        })
+    }
+
+    /// Like is_allowed but returns AddressListError::NotAllowed if not allowed.
+    /// Helper for a nice one-line auth check.
+    pub fn assert_allowed(
+        &self,
+        storage: &dyn Storage,
+        addr: &Addr,
+        block: &BlockInfo,
+    ) -> Result<(), AddressListError> {
+        if !self.is_allowed(storage, addr, block)? {
+            Err(AddressListError::NotAllowed {
+                addr: addr.to_string(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn execute_add_addresses<C, Q: CustomQuery>(
+        &self,
+        admin: &Admin,
+        deps: DepsMut<Q>,
+        info: MessageInfo,
+        addrs: Vec<Addr>,
+        expires: Expiration,
+    ) -> Result<Response<C>, AddressListError>
+    where
+        C: Clone + fmt::Debug + PartialEq + JsonSchema,
+    {
+        admin.assert_admin(deps.as_ref(), &info.sender)?;
+        self.add_addresses(deps.storage, &addrs, expires)?;
+
+        let attributes = vec![
+            attr("action", "add_addresses"),
+            attr("addresses", join_addrs(&addrs)),
+            attr("expires", expires.to_string()),
+            attr("sender", info.sender),
+        ];
+        Ok(Response::new().add_attributes(attributes))
+    }
+
+    pub fn execute_remove_addresses<C, Q: CustomQuery>(
+        &self,
+        admin: &Admin,
+        deps: DepsMut<Q>,
+        info: MessageInfo,
+        addrs: Vec<Addr>,
+    ) -> Result<Response<C>, AddressListError>
+    where
+        C: Clone + fmt::Debug + PartialEq + JsonSchema,
+    {
+        admin.assert_admin(deps.as_ref(), &info.sender)?;
+        self.remove_addresses(deps.storage, &addrs)?;
+
+        let attributes = vec![
+            attr("action", "remove_addresses"),
+            attr("addresses", join_addrs(&addrs)),
+            attr("sender", info.sender),
+        ];
+        Ok(Response::new().add_attributes(attributes))
+    }
+
+    /// Lists all entries in ascending address order, including expired ones
+    pub fn query_list<Q: CustomQuery>(
+        &self,
+        deps: Deps<Q>,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    ) -> StdResult<AddressListResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = calc_range_start(start_after).map(Bound::ExclusiveRaw);
+
+        let entries = self
+            .entries
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                let (addr, expires) = item?;
+                Ok(AddressListEntry {
+                    address: addr.into(),
+                    expires,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+        Ok(AddressListResponse { entries })
+    }
+
+    // Return true if the address is allowed at the given block
+    pub fn query_allowed<Q: CustomQuery>(
+        &self,
+        deps: Deps<Q>,
+        addr: &Addr,
+        block: &BlockInfo,
+    ) -> StdResult<bool> {
+        self.is_allowed(deps.storage, addr, block)
+    }
+}
+
+fn join_addrs(addrs: &[Addr]) -> String {
+    addrs.iter().map(Addr::as_str).collect::<Vec<_>>().join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{message_info, mock_dependencies, mock_env};
+    use cosmwasm_std::Empty;
+    use std::slice;
+
+    #[test]
+    fn allow_mode_checks() {
+        let mut deps = mock_dependencies();
+        let list = AddressList::new("kyc", AddressListMode::Allow);
+        let block = mock_env().block;
+
+        let alice = Addr::unchecked("alice");
+        let bob = Addr::unchecked("bob");
+        let carl = Addr::unchecked("carl");
+
+        // nobody is allowed on an empty allowlist
+        assert!(!list.is_allowed(&deps.storage, &alice, &block).unwrap());
+
+        list.add_addresses(
+            &mut deps.storage,
+            slice::from_ref(&alice),
+            Expiration::Never {},
+        )
+        .unwrap();
+        list.add_addresses(
+            &mut deps.storage,
+            slice::from_ref(&bob),
+            Expiration::AtHeight(block.height),
+        )
+        .unwrap();
+
+        list.assert_allowed(&deps.storage, &alice, &block).unwrap();
+        // bob's entry is already expired
+        let err = list
+            .assert_allowed(&deps.storage, &bob, &block)
+            .unwrap_err();
+        assert!(matches!(err, AddressListError::NotAllowed { addr } if addr == "bob"));
+        let err = list
+            .assert_allowed(&deps.storage, &carl, &block)
+            .unwrap_err();
+        assert!(matches!(err, AddressListError::NotAllowed { addr } if addr == "carl"));
+
+        // re-adding refreshes the expiration
+        list.add_addresses(
+            &mut deps.storage,
+            slice::from_ref(&bob),
+            Expiration::AtHeight(block.height + 1),
+        )
+        .unwrap();
+        list.assert_allowed(&deps.storage, &bob, &block).unwrap();
+    }
+
+    #[test]
+    fn deny_mode_checks() {
+        let mut deps = mock_dependencies();
+        let list = AddressList::new("sanctioned", AddressListMode::Deny);
+        let mut block = mock_env().block;
+
+        let alice = Addr::unchecked("alice");
+        let bob = Addr::unchecked("bob");
+
+        list.add_addresses(
+            &mut deps.storage,
+            slice::from_ref(&alice),
+            Expiration::AtHeight(block.height + 10),
+        )
+        .unwrap();
+
+        let err = list
+            .assert_allowed(&deps.storage, &alice, &block)
+            .unwrap_err();
+        assert!(matches!(err, AddressListError::NotAllowed { .. }));
+        list.assert_allowed(&deps.storage, &bob, &block).unwrap();
+
+        // once the entry expired, alice is no longer blocked
+        block.height += 10;
+        list.assert_allowed(&deps.storage, &alice, &block).unwrap();
+    }
+
+    #[test]
+    fn remove_addresses_is_atomic() {
+        let mut deps = mock_dependencies();
+        let list = AddressList::new("list", AddressListMode::Allow);
+        let block = mock_env().block;
+
+        let alice = Addr::unchecked("alice");
+        let bob = Addr::unchecked("bob");
+        list.add_addresses(
+            &mut deps.storage,
+            slice::from_ref(&alice),
+            Expiration::Never {},
+        )
+        .unwrap();
+
+        // bob is not listed, so nothing is removed
+        let err = list
+            .remove_addresses(&mut deps.storage, &[alice.clone(), bob.clone()])
+            .unwrap_err();
+        assert!(matches!(err, AddressListError::NotListed { addr } if addr == "bob"));
+        assert!(list.is_listed(&deps.storage, &alice, &block).unwrap());
+
+        list.remove_addresses(&mut deps.storage, slice::from_ref(&alice))
+            .unwrap();
+        assert!(!list.is_listed(&deps.storage, &alice, &block).unwrap());
+    }
+
+    #[test]
+    fn execute_requires_admin() {
+        let mut deps = mock_dependencies();
+        let admin = Admin::new("admin");
+        let list = AddressList::new("list", AddressListMode::Allow);
+        let block = mock_env().block;
+
+        let owner = Addr::unchecked("owner");
+        let imposter = Addr::unchecked("imposter");
+        let friends = vec![Addr::unchecked("alice"), Addr::unchecked("bob")];
+        admin.set(deps.as_mut(), Some(owner.clone())).unwrap();
+
+        let info = message_info(&imposter, &[]);
+        let err = list
+            .execute_add_addresses::<Empty, Empty>(
+                &admin,
+                deps.as_mut(),
+                info,
+                friends.clone(),
+                Expiration::Never {},
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AddressListError::Admin(AdminError::NotAdmin {})
+        ));
+
+        let info = message_info(&owner, &[]);
+        let res = list
+            .execute_add_addresses::<Empty, Empty>(
+                &admin,
+                deps.as_mut(),
+                info,
+                friends.clone(),
+                Expiration::Never {},
+            )
+            .unwrap();
+        assert_eq!(res.attributes[1], attr("addresses", "alice,bob"));
+        for friend in &friends {
+            assert!(list.query_allowed(deps.as_ref(), friend, &block).unwrap());
+        }
+
+        let info = message_info(&imposter, &[]);
+        let err = list
+            .execute_remove_addresses::<Empty, Empty>(&admin, deps.as_mut(), info, friends.clone())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AddressListError::Admin(AdminError::NotAdmin {})
+        ));
+
+        let info = message_info(&owner, &[]);
+        list.execute_remove_addresses::<Empty, Empty>(&admin, deps.as_mut(), info, friends)
+            .unwrap();
+        let res = list.query_list(deps.as_ref(), None, None).unwrap();
+        assert_eq!(res.entries.len(), 0);
+    }
+
+    #[test]
+    fn query_list_paginates() {
+        let mut deps = mock_dependencies();
+        let list = AddressList::new("list", AddressListMode::Deny);
+
+        let addrs: Vec<_> = (0..45)
+            .map(|i| Addr::unchecked(format!("addr{i:0>4}")))
+            .collect();
+        list.add_addresses(&mut deps.storage, &addrs, Expiration::AtHeight(1000))
+            .unwrap();
+
+        // default limit
+        let res = list.query_list(deps.as_ref(), None, None).unwrap();
+        assert_eq!(res.entries.len(), DEFAULT_LIMIT as usize);
+        assert_eq!(res.entries[0].address, "addr0000");
+
+        // capped at max limit
+        let res = list.query_list(deps.as_ref(), None, Some(100)).unwrap();
+        assert_eq!(res.entries.len(), MAX_LIMIT as usize);
+
+        // continue after the last element
+        let last = Addr::unchecked(res.entries.last().unwrap().address.clone());
+        let res = list
+            .query_list(deps.as_ref(), Some(last), Some(100))
+            .unwrap();
+        assert_eq!(res.entries.len(), 15);
+        assert_eq!(
+            res.entries[0],
+            AddressListEntry {
+                address: "addr0030".to_string(),
+                expires: Expiration::AtHeight(1000),
+            }
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, BlockInfo, CustomQuery, Deps, DepsMut, MessageInfo, Order, Response, StdResult, Storage,
+    Timestamp,
+};
+use cw_storage_plus::{Bound, Item, Map, Namespace};
+
+use crate::admin::{Admin, AdminError};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+/// A config value together with the block it was set in
+#[cw_serde]
+pub struct ConfigChange<T> {
+    pub height: u64,
+    pub time: Timestamp,
+    pub config: T,
+}
+
+#[cw_serde]
+pub struct ConfigHistoryResponse<T> {
+    pub changes: Vec<ConfigChange<T>>,
+}
+
+/// Config stores the current contract configuration, and keeps every previous
+/// value indexed by the height it was set at, so it can be looked up later.
+pub struct Config<T> {
+    current: Item<T>,
+    history: Map<u64, ConfigChange<T>>,
+}
+
+impl<T> Config<T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    pub const fn new(config_key: &'static str, history_key: &'static str) -> Self {
+        Config {
+            current: Item::new(config_key),
+            history: Map::new(history_key),
+        }
+    }
+
+    pub fn new_dyn(config_key: impl Into<Namespace>, history_key: impl Into<Namespace>) -> Self {
+        Config {
+            current: Item::new_dyn(config_key),
+            history: Map::new_dyn(history_key),
+        }
+    }
+
+    /// Sets the config and records the change. Several changes in the same block
+    /// keep only the last one in history, as that is what the block ends with.
+    pub fn save(&self, storage: &mut dyn Storage, block: &BlockInfo, config: &T) -> StdResult<()> {
+        self.current.save(storage, config)?;
+        let change = ConfigChange {
+            height: block.height,
+            time: block.time,
+            config: config.clone(),
+        };
+        self.history.save(storage, block.height, &change)
+    }
+
+    pub fn load(&self, storage: &dyn Storage) -> StdResult<T> {
+        self.current.load(storage)
+    }
+
+    pub fn may_load(&self, storage: &dyn Storage) -> StdResult<Option<T>> {
+        self.current.may_load(storage)
+    }
+
+    /// Returns the config that was active at the end of the given height,
+    /// or None if it was not set yet
+    pub fn may_load_at_height(&self, storage: &dyn Storage, height: u64) -> StdResult<Option<T>> {
+        self.history
+            .range(
+                storage,
+                None,
+                Some(Bound::inclusive(height)),
+                Order::Descending,
+            )
+            .next()
+            .transpose()
+            .map(|change| change.map(|(_, c)| c.config))
+    }
+
+    pub fn execute_update_config<C, Q: CustomQuery>(
+        &self,
+        admin: &Admin,
+        deps: DepsMut<Q>,
+        info: MessageInfo,
+        block: &BlockInfo,
+        config: T,
+    ) -> Result<Response<C>, AdminError>
+    where
+        C: Clone + fmt::Debug + PartialEq + JsonSchema,
+    {
+        admin.assert_admin(deps.as_ref(), &info.sender)?;
+        self.save(deps.storage, block, &config)?;
+
+        let attributes = vec![
+            attr("action", "update_config"),
+            attr("height", block.height.to_string()),
+            attr("sender", info.sender),
+        ];
+        Ok(Response::new().add_attributes(attributes))
+    }
+
+    pub fn query_config<Q: CustomQuery>(&self, deps: Deps<Q>) -> StdResult<T> {
+        self.load(deps.storage)
+    }
+
+    pub fn query_config_at_height<Q: CustomQuery>(
+        &self,
+        deps: Deps<Q>,
+        height: u64,
+    ) -> StdResult<Option<T>> {
+        self.may_load_at_height(deps.storage, height)
+    }
+
+    /// Lists all changes in ascending height order
+    pub fn query_history<Q: CustomQuery>(
+        &self,
+        deps: Deps<Q>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<ConfigHistoryResponse<T>> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+
+        let changes = self
+            .history
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(_, change)| change))
+            .collect::<StdResult<_>>()?;
+        Ok(ConfigHistoryResponse { changes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{message_info, mock_dependencies, mock_env};
+    use cosmwasm_std::{Addr, Empty};
+
+    #[cw_serde]
+    struct Settings {
+        fee_bps: u16,
+    }
+
+    const CONFIG: Config<Settings> = Config::new("config", "config_history");
+
+    #[test]
+    fn save_records_history() {
+        let mut deps = mock_dependencies();
+        let mut block = mock_env().block;
+        let start = block.height;
+
+        assert_eq!(CONFIG.may_load(&deps.storage).unwrap(), None);
+        CONFIG
+            .save(&mut deps.storage, &block, &Settings { fee_bps: 10 })
+            .unwrap();
+
+        block.height += 5;
+        CONFIG
+            .save(&mut deps.storage, &block, &Settings { fee_bps: 20 })
+            .unwrap();
+        // a second change in the same block replaces the first
+        CONFIG
+            .save(&mut deps.storage, &block, &Settings { fee_bps: 30 })
+            .unwrap();
+
+        assert_eq!(CONFIG.load(&deps.storage).unwrap().fee_bps, 30);
+
+        let at = |height| {
+            CONFIG
+                .may_load_at_height(&deps.storage, height)
+                .unwrap()
+                .map(|s| s.fee_bps)
+        };
+        assert_eq!(at(start - 1), None);
+        assert_eq!(at(start), Some(10));
+        assert_eq!(at(start + 4), Some(10));
+        assert_eq!(at(start + 5), Some(30));
+        assert_eq!(at(start + 500), Some(30));
+    }
+
+    #[test]
+    fn update_requires_admin() {
+        let mut deps = mock_dependencies();
+        let block = mock_env().block;
+        let admin = Admin::new("admin");
+        let owner = Addr::unchecked("owner");
+        let imposter = Addr::unchecked("imposter");
+        admin.set(deps.as_mut(), Some(owner.clone())).unwrap();
+
+        let err = CONFIG
+            .execute_update_config::<Empty, Empty>(
+                &admin,
+                deps.as_mut(),
+                message_info(&imposter, &[]),
+                &block,
+                Settings { fee_bps: 1 },
+            )
+            .unwrap_err();
+        assert!(matches!(err, AdminError::NotAdmin {}));
+
+        CONFIG
+            .execute_update_config::<Empty, Empty>(
+                &admin,
+                deps.as_mut(),
+                message_info(&owner, &[]),
+                &block,
+                Settings { fee_bps: 1 },
+            )
+            .unwrap();
+        let config = CONFIG.query_config(deps.as_ref()).unwrap();
+        assert_eq!(config, Settings { fee_bps: 1 });
+        let config = CONFIG
+            .query_config_at_height(deps.as_ref(), block.height)
+            .unwrap();
+        assert_eq!(config, Some(Settings { fee_bps: 1 }));
+    }
+
+    #[test]
+    fn query_history_paginates() {
+        let mut deps = mock_dependencies();
+        let mut block = mock_env().block;
+        let start = block.height;
+
+        for fee_bps in 0..15 {
+            CONFIG
+                .save(&mut deps.storage, &block, &Settings { fee_bps })
+                .unwrap();
+            block.height += 1;
+            block.time = block.time.plus_seconds(5);
+        }
+
+        let res = CONFIG.query_history(deps.as_ref(), None, None).unwrap();
+        assert_eq!(res.changes.len(), DEFAULT_LIMIT as usize);
+        assert_eq!(res.changes[0].height, start);
+
+        let last = res.changes.last().unwrap().height;
+        let res = CONFIG
+            .query_history(deps.as_ref(), Some(last), None)
+            .unwrap();
+        assert_eq!(res.changes.len(), 5);
+        assert_eq!(
+            res.changes[0],
+            ConfigChange {
+                height: start + 10,
+                time: mock_env().block.time.plus_seconds(50),
+                config: Settings { fee_bps: 10 },
+            }
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use thiserror::Error;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, BlockInfo, CustomQuery, Deps, DepsMut, MessageInfo, Response, StdError, StdResult,
+    Storage,
+};
+use cw_storage_plus::{Item, Map, Namespace};
+use cw_utils::{Duration, ExpirationError, RecurringError, RecurringSchedule, Scheduled};
+
+use crate::admin::{Admin, AdminError};
+
+#[derive(Error, Debug)]
+pub enum EpochError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Admin(#[from] AdminError),
+
+    #[error("{0}")]
+    Schedule(#[from] RecurringError),
+
+    #[error("{0}")]
+    Expiration(#[from] ExpirationError),
+
+    #[error("Epoch length cannot be zero")]
+    ZeroLength {},
+
+    #[error("Cannot change epoch length between height and time")]
+    UnitChange {},
+}
+
+/// The epoch that was current when last accessed
+#[cw_serde]
+pub struct EpochState {
+    pub epoch: u64,
+    pub start: Scheduled,
+    pub length: Duration,
+    /// Length of all epochs after the current one, if it was changed
+    pub next_length: Option<Duration>,
+}
+
+impl EpochState {
+    pub fn end(&self) -> StdResult<Scheduled> {
+        self.start + self.length
+    }
+
+    /// Moves to the epoch the given block is in, returns true if it changed
+    fn advance(&mut self, block: &BlockInfo) -> Result<bool, EpochError> {
+        let start = self.start;
+        if let Some(next_length) = self.next_length {
+            let end = self.end()?;
+            if !end.is_triggered(block) {
+                return Ok(false);
+            }
+            self.epoch += 1;
+            self.start = end;
+            self.length = next_length;
+            self.next_length = None;
+        }
+
+        let schedule = RecurringSchedule {
+            start: self.start,
+            every: self.length,
+            end: None,
+        };
+        // every occurrence after the start is a boundary we crossed
+        let passed = schedule.missed_since(&self.start, block)?;
+        if passed > 0 {
+            self.epoch += passed;
+            self.start = self.start.checked_add(self.length.checked_mul(passed)?)?;
+        }
+        Ok(self.start != start)
+    }
+}
+
+#[cw_serde]
+pub struct EpochResponse {
+    pub epoch: u64,
+    pub start: Scheduled,
+    pub end: Scheduled,
+    pub length: Duration,
+    pub next_length: Option<Duration>,
+}
+
+/// Epochs splits the contract lifetime into consecutive periods of a fixed length,
+/// counted from 0. The stored state is advanced lazily whenever it is accessed,
+/// so no message is needed at the epoch boundaries.
+/// Data can be snapshotted per epoch, e.g. reward totals.
+pub struct Epochs<T> {
+    state: Item<EpochState>,
+    snapshots: Map<u64, T>,
+}
+
+impl<T> Epochs<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    pub const fn new(state_key: &'static str, snapshots_key: &'static str) -> Self {
+        Epochs {
+            state: Item::new(state_key),
+            snapshots: Map::new(snapshots_key),
+        }
+    }
+
+    pub fn new_dyn(state_key: impl Into<Namespace>, snapshots_key: impl Into<Namespace>) -> Self {
+        Epochs {
+            state: Item::new_dyn(state_key),
+            snapshots: Map::new_dyn(snapshots_key),
+        }
+    }
+
+    /// Starts epoch 0 at the given block
+    pub fn initialize(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        length: Duration,
+    ) -> Result<(), EpochError> {
+        let state = EpochState {
+            epoch: 0,
+            start: start_at(block, &length),
+            length,
+            next_length: None,
+        };
+        validate_length(&state, &length)?;
+        Ok(self.state.save(storage, &state)?)
+    }
+
+    /// Returns the epoch the given block is in, storing the advanced state
+    pub fn current_epoch(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+    ) -> Result<EpochState, EpochError> {
+        let mut state = self.state.load(storage)?;
+        if state.advance(block)? {
+            self.state.save(storage, &state)?;
+        }
+        Ok(state)
+    }
+
+    /// Like `current_epoch`, but without storing the advanced state, for queries
+    pub fn peek_epoch(
+        &self,
+        storage: &dyn Storage,
+        block: &BlockInfo,
+    ) -> Result<EpochState, EpochError> {
+        let mut state = self.state.load(storage)?;
+        state.advance(block)?;
+        Ok(state)
+    }
+
+    /// Changes the length of all epochs after the current one
+    pub fn set_length(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        length: Duration,
+    ) -> Result<EpochState, EpochError> {
+        let mut state = self.current_epoch(storage, block)?;
+        validate_length(&state, &length)?;
+        state.next_length = Some(length);
+        self.state.save(storage, &state)?;
+        Ok(state)
+    }
+
+    /// Stores data for the epoch the given block is in, replacing earlier data
+    /// of the same epoch. Returns that epoch.
+    pub fn save_snapshot(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        data: &T,
+    ) -> Result<u64, EpochError> {
+        let epoch = self.current_epoch(storage, block)?.epoch;
+        self.snapshots.save(storage, epoch, data)?;
+        Ok(epoch)
+    }
+
+    pub fn may_load_snapshot(&self, storage: &dyn Storage, epoch: u64) -> StdResult<Option<T>> {
+        self.snapshots.may_load(storage, epoch)
+    }
+
+    /// The new length only takes effect at the next epoch boundary
+    pub fn execute_set_length<C, Q: CustomQuery>(
+        &self,
+        admin: &Admin,
+        deps: DepsMut<Q>,
+        info: MessageInfo,
+        block: &BlockInfo,
+        length: Duration,
+    ) -> Result<Response<C>, EpochError>
+    where
+        C: Clone + fmt::Debug + PartialEq + JsonSchema,
+    {
+        admin.assert_admin(deps.as_ref(), &info.sender)?;
+        let state = self.set_length(deps.storage, block, length)?;
+
+        let attributes = vec![
+            attr("action", "set_epoch_length"),
+            attr("length", length.to_string()),
+            attr("effective_epoch", (state.epoch + 1).to_string()),
+            attr("sender", info.sender),
+        ];
+        Ok(Response::new().add_attributes(attributes))
+    }
+
+    pub fn query_epoch<Q: CustomQuery>(
+        &self,
+        deps: Deps<Q>,
+        block: &BlockInfo,
+    ) -> StdResult<EpochResponse> {
+        let state = self
+            .peek_epoch(deps.storage, block)
+            .map_err(|err| match err {
+                EpochError::Std(err) => err,
+                err => StdError::msg(err),
+            })?;
+        Ok(EpochResponse {
+            epoch: state.epoch,
+            start: state.start,
+            end: state.end()?,
+            length: state.length,
+            next_length: state.next_length,
+        })
+    }
+
+    pub fn query_snapshot<Q: CustomQuery>(
+        &self,
+        deps: Deps<Q>,
+        epoch: u64,
+    ) -> StdResult<Option<T>> {
+        self.may_load_snapshot(deps.storage, epoch)
+    }
+}
+
+fn start_at(block: &BlockInfo, length: &Duration) -> Scheduled {
+    match length {
+        Duration::Height(_) => Scheduled::AtHeight(block.height),
+        Duration::Time(_) => Scheduled::AtTime(block.time),
+    }
+}
+
+fn validate_length(state: &EpochState, length: &Duration) -> Result<(), EpochError> {
+    match (state.start, length) {
+        (_, Duration::Height(0) | Duration::Time(0)) => Err(EpochError::ZeroLength {}),
+        (Scheduled::AtHeight(_), Duration::Height(_))
+        | (Scheduled::AtTime(_), Duration::Time(_)) => Ok(()),
+        _ => Err(EpochError::UnitChange {}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{message_info, mock_dependencies, mock_env};
+    use cosmwasm_std::{Addr, Empty, Uint128};
+
+    const EPOCHS: Epochs<Uint128> = Epochs::new("epoch", "epoch_rewards");
+
+    fn at_height(height: u64) -> BlockInfo {
+        BlockInfo {
+            height,
+            ..mock_env().block
+        }
+    }
+
+    #[test]
+    fn advances_lazily() {
+        let mut deps = mock_dependencies();
+        EPOCHS
+            .initialize(&mut deps.storage, &at_height(100), Duration::Height(10))
+            .unwrap();
+
+        let epoch = |storage: &mut dyn Storage, height| {
+            EPOCHS.current_epoch(storage, &at_height(height)).unwrap()
+        };
+        assert_eq!(epoch(&mut deps.storage, 100).epoch, 0);
+        assert_eq!(epoch(&mut deps.storage, 109).epoch, 0);
+        let state = epoch(&mut deps.storage, 110);
+        assert_eq!(state.epoch, 1);
+        assert_eq!(state.start, Scheduled::AtHeight(110));
+
+        // several epochs passed without access
+        let state = epoch(&mut deps.storage, 145);
+        assert_eq!(state.epoch, 4);
+        assert_eq!(state.start, Scheduled::AtHeight(140));
+        assert_eq!(state.end().unwrap(), Scheduled::AtHeight(150));
+
+        // peeking does not store anything
+        let res = EPOCHS.query_epoch(deps.as_ref(), &at_height(171)).unwrap();
+        assert_eq!(res.epoch, 7);
+        assert_eq!(res.start, Scheduled::AtHeight(170));
+        assert_eq!(res.end, Scheduled::AtHeight(180));
+        let stored = EPOCHS.peek_epoch(&deps.storage, &at_height(145)).unwrap();
+        assert_eq!(stored.epoch, 4);
+    }
+
+    #[test]
+    fn time_based_epochs() {
+        let mut deps = mock_dependencies();
+        let mut block = mock_env().block;
+        let start = block.time;
+        EPOCHS
+            .initialize(&mut deps.storage, &block, cw_utils::DAY)
+            .unwrap();
+
+        block.time = start.plus_seconds(3 * 86400 + 5);
+        let state = EPOCHS.current_epoch(&mut deps.storage, &block).unwrap();
+        assert_eq!(state.epoch, 3);
+        assert_eq!(
+            state.start,
+            Scheduled::AtTime(start.plus_seconds(3 * 86400))
+        );
+    }
+
+    #[test]
+    fn length_change_applies_at_next_boundary() {
+        let mut deps = mock_dependencies();
+        let admin = Admin::new("admin");
+        let owner = Addr::unchecked("owner");
+        admin.set(deps.as_mut(), Some(owner.clone())).unwrap();
+        EPOCHS
+            .initialize(&mut deps.storage, &at_height(100), Duration::Height(10))
+            .unwrap();
+
+        let err = EPOCHS
+            .execute_set_length::<Empty, Empty>(
+                &admin,
+                deps.as_mut(),
+                message_info(&Addr::unchecked("imposter"), &[]),
+                &at_height(115),
+                Duration::Height(50),
+            )
+            .unwrap_err();
+        assert!(matches!(err, EpochError::Admin(AdminError::NotAdmin {})));
+
+        let res = EPOCHS
+            .execute_set_length::<Empty, Empty>(
+                &admin,
+                deps.as_mut(),
+                message_info(&owner, &[]),
+                &at_height(115),
+                Duration::Height(50),
+            )
+            .unwrap();
+        assert_eq!(res.attributes[2], attr("effective_epoch", "2"));
+
+        // current epoch keeps its length
+        let state = EPOCHS
+            .current_epoch(&mut deps.storage, &at_height(119))
+            .unwrap();
+        assert_eq!(state.epoch, 1);
+        assert_eq!(state.end().unwrap(), Scheduled::AtHeight(120));
+        assert_eq!(state.next_length, Some(Duration::Height(50)));
+
+        // later ones use the new length
+        let state = EPOCHS
+            .current_epoch(&mut deps.storage, &at_height(175))
+            .unwrap();
+        assert_eq!(state.epoch, 3);
+        assert_eq!(state.start, Scheduled::AtHeight(170));
+        assert_eq!(state.length, Duration::Height(50));
+        assert_eq!(state.next_length, None);
+
+        let err = EPOCHS
+            .set_length(&mut deps.storage, &at_height(175), cw_utils::HOUR)
+            .unwrap_err();
+        assert!(matches!(err, EpochError::UnitChange {}));
+        let err = EPOCHS
+            .set_length(&mut deps.storage, &at_height(175), Duration::Height(0))
+            .unwrap_err();
+        assert!(matches!(err, EpochError::ZeroLength {}));
+    }
+
+    #[test]
+    fn snapshots_per_epoch() {
+        let mut deps = mock_dependencies();
+        EPOCHS
+            .initialize(&mut deps.storage, &at_height(100), Duration::Height(10))
+            .unwrap();
+
+        let epoch = EPOCHS
+            .save_snapshot(&mut deps.storage, &at_height(105), &Uint128::new(5))
+            .unwrap();
+        assert_eq!(epoch, 0);
+        let epoch = EPOCHS
+            .save_snapshot(&mut deps.storage, &at_height(125), &Uint128::new(7))
+            .unwrap();
+        assert_eq!(epoch, 2);
+
+        assert_eq!(
+            EPOCHS.query_snapshot(deps.as_ref(), 0).unwrap(),
+            Some(Uint128::new(5))
+        );
+        assert_eq!(EPOCHS.query_snapshot(deps.as_ref(), 1).unwrap(), None);
+        assert_eq!(
+            EPOCHS.may_load_snapshot(&deps.storage, 2).unwrap(),
+            Some(Uint128::new(7))
+        );
+    }
+}
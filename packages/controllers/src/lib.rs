@@ -13,11 +13,37 @@ goes for smaller scale units.
 Supported controllers:
 
 * Admin (`UpdateAdmin` handler, `Admin` querier, set_admin and is_admin methods)
+* AddressList (allow/deny lists with expiring entries, assert_allowed and paginated listing)
+* Config (admin-gated config updates, with history queryable by height)
+* Epochs (lazily advancing epoch counter with per-epoch snapshots and deferred length changes)
+* Lock (single-flight guard held until the last submessage reply)
+* Nonces (per-address replay protection for off-chain signed payloads)
+* Timelock (queue messages behind a minimum delay, executable by anyone once ready)
+* Vesting (per-address linear grants with a cliff, revocable by the admin)
 */
+mod address_list;
 mod admin;
 mod claim;
+mod config;
+mod epochs;
 mod hooks;
+mod lock;
+mod nonces;
+mod timelock;
+mod vesting;
 
+pub use address_list::{
+    AddressList, AddressListEntry, AddressListError, AddressListMode, AddressListResponse,
+};
 pub use admin::{Admin, AdminError, AdminResponse};
 pub use claim::{Claim, Claims, ClaimsResponse};
+pub use config::{Config, ConfigChange, ConfigHistoryResponse};
+pub use epochs::{EpochError, EpochResponse, EpochState, Epochs};
 pub use hooks::{HookError, Hooks, HooksResponse};
+pub use lock::{Lock, LockError};
+pub use nonces::{
+    verify_signature, NonceError, NonceResponse, Nonces, PublicKey, PublicKeyResponse,
+    SignedPayload,
+};
+pub use timelock::{Operation, OperationStatus, OperationsResponse, Timelock, TimelockError};
+pub use vesting::{Grant, GrantError, GrantInfo, GrantResponse, GrantsResponse, Vesting};
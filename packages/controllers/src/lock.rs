@@ -0,0 +1,268 @@
+use thiserror::Error;
+
+use cosmwasm_std::{
+    CosmosMsg, CustomQuery, Deps, Empty, Reply, Response, StdError, StdResult, Storage, SubMsg,
+    SubMsgResult,
+};
+use cw_storage_plus::{Map, Namespace};
+
+#[derive(Error, Debug)]
+pub enum LockError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Lock '{key}' is already held by an operation in flight")]
+    AlreadyLocked { key: String },
+
+    #[error("Lock '{key}' is not held")]
+    NotLocked { key: String },
+
+    #[error("Operation guarded by lock '{key}' failed: {error}")]
+    OperationFailed { key: String, error: String },
+}
+
+/// Single-flight guard, preventing re-entry while submessages of an operation are pending.
+///
+/// There is no need to release the lock when an operation fails: the transaction
+/// is reverted, together with the `acquire` call.
+pub struct Lock(Map<&'static str, Empty>);
+
+impl Lock {
+    pub const fn new(storage_key: &'static str) -> Self {
+        Lock(Map::new(storage_key))
+    }
+
+    pub fn new_dyn(storage_key: impl Into<Namespace>) -> Self {
+        Lock(Map::new_dyn(storage_key))
+    }
+
+    pub fn is_locked(&self, storage: &dyn Storage, key: &str) -> bool {
+        self.0.has(storage, key)
+    }
+
+    pub fn acquire(&self, storage: &mut dyn Storage, key: &str) -> Result<(), LockError> {
+        if self.is_locked(storage, key) {
+            return Err(LockError::AlreadyLocked {
+                key: key.to_string(),
+            });
+        }
+        Ok(self.0.save(storage, key, &Empty {})?)
+    }
+
+    pub fn release(&self, storage: &mut dyn Storage, key: &str) -> Result<(), LockError> {
+        if !self.is_locked(storage, key) {
+            return Err(LockError::NotLocked {
+                key: key.to_string(),
+            });
+        }
+        self.0.remove(storage, key);
+        Ok(())
+    }
+
+    /// Acquires the lock and appends `last` to the response as a submessage replying
+    /// with `release_id` on success and error. As submessages are executed in order,
+    /// this reply is the last step of the operation and must call `release_on_reply`.
+    pub fn guarded_response<C>(
+        &self,
+        storage: &mut dyn Storage,
+        key: &str,
+        response: Response<C>,
+        last: impl Into<CosmosMsg<C>>,
+        release_id: u64,
+    ) -> Result<Response<C>, LockError> {
+        self.acquire(storage, key)?;
+        Ok(response.add_submessage(SubMsg::reply_always(last, release_id)))
+    }
+
+    /// Releases the lock if this is the reply to the trailing submessage added by
+    /// `guarded_response`. Returns true if the lock was released.
+    ///
+    /// If the trailing submessage failed, this returns `OperationFailed`. Propagate it
+    /// from the reply entry point so the whole operation is reverted, rather than
+    /// committing it as if it succeeded.
+    pub fn release_on_reply(
+        &self,
+        storage: &mut dyn Storage,
+        key: &str,
+        reply: &Reply,
+        release_id: u64,
+    ) -> Result<bool, LockError> {
+        if reply.id != release_id {
+            return Ok(false);
+        }
+        self.release(storage, key)?;
+        match &reply.result {
+            SubMsgResult::Ok(_) => Ok(true),
+            SubMsgResult::Err(error) => Err(LockError::OperationFailed {
+                key: key.to_string(),
+                error: error.clone(),
+            }),
+        }
+    }
+
+    pub fn query_locked<Q: CustomQuery>(&self, deps: Deps<Q>, key: &str) -> StdResult<bool> {
+        Ok(self.is_locked(deps.storage, key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::mock_dependencies;
+    use cosmwasm_std::{
+        to_json_binary, Binary, MsgResponse, ReplyOn, SubMsgResponse, SubMsgResult, WasmMsg,
+    };
+    use cw_utils::parse_execute_response_data;
+
+    const LOCK: Lock = Lock::new("lock");
+    const KEY: &str = "swap";
+    const SWAP_REPLY_ID: u64 = 1;
+    const RELEASE_REPLY_ID: u64 = 2;
+
+    fn execute_msg(contract: &str) -> WasmMsg {
+        WasmMsg::Execute {
+            contract_addr: contract.to_string(),
+            msg: to_json_binary(&Empty {}).unwrap(),
+            funds: vec![],
+        }
+    }
+
+    // builds a reply as the chain would for a successful `MsgExecuteContract`
+    fn execute_reply(id: u64, data: &[u8]) -> Reply {
+        // protobuf encoding of MsgExecuteContractResponse { data }
+        let mut encoded = vec![0x0a, data.len() as u8];
+        encoded.extend_from_slice(data);
+        #[allow(deprecated)]
+        let response = SubMsgResponse {
+            events: vec![],
+            data: None,
+            msg_responses: vec![MsgResponse {
+                type_url: "/cosmwasm.wasm.v1.MsgExecuteContractResponse".to_string(),
+                value: Binary::from(encoded),
+            }],
+        };
+        Reply {
+            id,
+            payload: Binary::default(),
+            gas_used: 1234,
+            result: SubMsgResult::Ok(response),
+        }
+    }
+
+    fn reply_data(reply: &Reply) -> Option<Binary> {
+        let response = reply.result.clone().unwrap();
+        parse_execute_response_data(&response.msg_responses[0].value)
+            .unwrap()
+            .data
+    }
+
+    #[test]
+    fn acquire_and_release() {
+        let mut deps = mock_dependencies();
+
+        assert!(!LOCK.is_locked(&deps.storage, KEY));
+        LOCK.acquire(&mut deps.storage, KEY).unwrap();
+        assert!(LOCK.query_locked(deps.as_ref(), KEY).unwrap());
+
+        let err = LOCK.acquire(&mut deps.storage, KEY).unwrap_err();
+        assert!(matches!(err, LockError::AlreadyLocked { key } if key == KEY));
+
+        // other keys are independent
+        LOCK.acquire(&mut deps.storage, "other").unwrap();
+
+        LOCK.release(&mut deps.storage, KEY).unwrap();
+        let err = LOCK.release(&mut deps.storage, KEY).unwrap_err();
+        assert!(matches!(err, LockError::NotLocked { key } if key == KEY));
+        LOCK.acquire(&mut deps.storage, KEY).unwrap();
+    }
+
+    #[test]
+    fn guarded_response_holds_lock_until_last_reply() {
+        let mut deps = mock_dependencies();
+
+        // execute: dispatch the swap, then the trailing step
+        let response = Response::<Empty>::new()
+            .add_submessage(SubMsg::reply_on_success(execute_msg("pool"), SWAP_REPLY_ID));
+        let res = LOCK
+            .guarded_response(
+                &mut deps.storage,
+                KEY,
+                response,
+                execute_msg("router"),
+                RELEASE_REPLY_ID,
+            )
+            .unwrap();
+        assert_eq!(res.messages.len(), 2);
+        assert_eq!(res.messages[1].id, RELEASE_REPLY_ID);
+        assert_eq!(res.messages[1].reply_on, ReplyOn::Always);
+
+        // the pool calls back into us before its reply: nested entry is rejected
+        let err = LOCK
+            .guarded_response(
+                &mut deps.storage,
+                KEY,
+                Response::<Empty>::new(),
+                execute_msg("router"),
+                RELEASE_REPLY_ID,
+            )
+            .unwrap_err();
+        assert!(matches!(err, LockError::AlreadyLocked { .. }));
+
+        // reply of the swap: still locked, as the trailing submessage is pending
+        let reply = execute_reply(SWAP_REPLY_ID, b"swapped");
+        assert_eq!(reply_data(&reply), Some(Binary::from(b"swapped")));
+        let released = LOCK
+            .release_on_reply(&mut deps.storage, KEY, &reply, RELEASE_REPLY_ID)
+            .unwrap();
+        assert!(!released);
+        assert!(LOCK.is_locked(&deps.storage, KEY));
+
+        // reply of the trailing submessage releases the lock
+        let reply = execute_reply(RELEASE_REPLY_ID, b"routed");
+        assert_eq!(reply_data(&reply), Some(Binary::from(b"routed")));
+        let released = LOCK
+            .release_on_reply(&mut deps.storage, KEY, &reply, RELEASE_REPLY_ID)
+            .unwrap();
+        assert!(released);
+        assert!(!LOCK.is_locked(&deps.storage, KEY));
+
+        // a new operation can start
+        LOCK.guarded_response(
+            &mut deps.storage,
+            KEY,
+            Response::<Empty>::new(),
+            execute_msg("router"),
+            RELEASE_REPLY_ID,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn failing_last_step_is_propagated() {
+        let mut deps = mock_dependencies();
+
+        LOCK.guarded_response(
+            &mut deps.storage,
+            KEY,
+            Response::<Empty>::new(),
+            execute_msg("router"),
+            RELEASE_REPLY_ID,
+        )
+        .unwrap();
+
+        let reply = Reply {
+            id: RELEASE_REPLY_ID,
+            payload: Binary::default(),
+            gas_used: 1234,
+            result: SubMsgResult::Err("slippage exceeded".to_string()),
+        };
+        let err = LOCK
+            .release_on_reply(&mut deps.storage, KEY, &reply, RELEASE_REPLY_ID)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LockError::OperationFailed { key, error } if key == KEY && error == "slippage exceeded"
+        ));
+    }
+}
@@ -0,0 +1,541 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use thiserror::Error;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, from_json, to_json_vec, Addr, Api, Binary, BlockInfo, CustomQuery, Deps, DepsMut, Env,
+    MessageInfo, Response, StdError, StdResult, Storage, VerificationError,
+};
+use cw_storage_plus::{Map, Namespace};
+use cw_utils::Expiration;
+
+/// The public key a payload was signed with, together with its signature scheme
+#[cw_serde]
+pub enum PublicKey {
+    /// Compressed (33 bytes) or uncompressed (65 bytes) secp256k1 key.
+    /// The signature is expected over the sha256 hash of the payload.
+    Secp256k1(Binary),
+    /// 32 bytes ed25519 key. The signature is expected over the raw payload.
+    Ed25519(Binary),
+}
+
+/// A payload signed off-chain. The signature covers the payload together with its nonce and
+/// deadline, bound to the verifying contract and chain, see `SignedPayload::sign_bytes`.
+#[cw_serde]
+pub struct SignedPayload<T> {
+    pub payload: T,
+    pub nonce: u64,
+    pub deadline: Option<Expiration>,
+}
+
+/// The document that is actually signed, so a signature cannot be replayed
+/// against another contract or chain
+#[derive(Serialize)]
+struct SignDoc<'a, T> {
+    chain_id: &'a str,
+    contract: &'a Addr,
+    deadline: &'a Option<Expiration>,
+    nonce: u64,
+    payload: &'a T,
+}
+
+/// Any JSON value without floats, with object keys sorted (`BTreeMap`), to bring
+/// serialized documents into canonical form independent of field declaration order
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum CanonicalJson {
+    Null,
+    Bool(bool),
+    Unsigned(u64),
+    Signed(i64),
+    String(String),
+    Array(Vec<CanonicalJson>),
+    Object(BTreeMap<String, CanonicalJson>),
+}
+
+impl<T: Serialize> SignedPayload<T> {
+    /// The bytes to sign for the given chain and verifying contract. This is the compact
+    /// JSON of `{"chain_id":..,"contract":..,"deadline":..,"nonce":..,"payload":..}`
+    /// with the keys of all objects, including those within the payload, sorted
+    /// lexicographically, so off-chain signers can rebuild it from the JSON alone.
+    pub fn sign_bytes(&self, chain_id: &str, contract: &Addr) -> StdResult<Vec<u8>> {
+        let doc = to_json_vec(&SignDoc {
+            chain_id,
+            contract,
+            deadline: &self.deadline,
+            nonce: self.nonce,
+            payload: &self.payload,
+        })?;
+        to_json_vec(&from_json::<CanonicalJson>(doc)?)
+    }
+}
+
+#[cw_serde]
+pub struct NonceResponse {
+    pub nonce: u64,
+}
+
+#[cw_serde]
+pub struct PublicKeyResponse {
+    pub public_key: Option<PublicKey>,
+}
+
+#[derive(Error, Debug)]
+pub enum NonceError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Verification(#[from] VerificationError),
+
+    #[error("Invalid nonce: expected {expected}, received {received}")]
+    InvalidNonce { expected: u64, received: u64 },
+
+    #[error("Signed payload expired at {deadline}")]
+    DeadlineExpired { deadline: Expiration },
+
+    #[error("Signature does not match the payload and public key")]
+    InvalidSignature {},
+
+    #[error("Public key has an invalid length")]
+    InvalidPublicKey {},
+
+    #[error("No public key registered for {signer}")]
+    NoPublicKey { signer: Addr },
+}
+
+/// Verifies that `signature` was produced by `public_key` over `message`
+pub fn verify_signature(
+    api: &dyn Api,
+    message: &[u8],
+    signature: &[u8],
+    public_key: &PublicKey,
+) -> Result<(), NonceError> {
+    let valid = match public_key {
+        PublicKey::Secp256k1(key) => {
+            let hash = Sha256::digest(message);
+            api.secp256k1_verify(&hash, signature, key)?
+        }
+        PublicKey::Ed25519(key) => api.ed25519_verify(message, signature, key)?,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(NonceError::InvalidSignature {})
+    }
+}
+
+/// Stores the next expected nonce for every address that has used one,
+/// and the public keys addresses registered to sign payloads with
+pub struct Nonces {
+    nonces: Map<&'static Addr, u64>,
+    keys: Map<&'static Addr, PublicKey>,
+}
+
+impl Nonces {
+    pub const fn new(nonces_key: &'static str, keys_key: &'static str) -> Self {
+        Nonces {
+            nonces: Map::new(nonces_key),
+            keys: Map::new(keys_key),
+        }
+    }
+
+    pub fn new_dyn(nonces_key: impl Into<Namespace>, keys_key: impl Into<Namespace>) -> Self {
+        Nonces {
+            nonces: Map::new_dyn(nonces_key),
+            keys: Map::new_dyn(keys_key),
+        }
+    }
+
+    /// Returns the nonce the next payload of this address must use (starting at 0)
+    pub fn current(&self, storage: &dyn Storage, addr: &Addr) -> StdResult<u64> {
+        Ok(self.nonces.may_load(storage, addr)?.unwrap_or_default())
+    }
+
+    /// Consumes the given nonce if it is the expected one and the deadline did not pass.
+    /// Each nonce can be used only once, and only in increasing order.
+    pub fn use_nonce(
+        &self,
+        storage: &mut dyn Storage,
+        addr: &Addr,
+        nonce: u64,
+        deadline: Option<Expiration>,
+        block: &BlockInfo,
+    ) -> Result<(), NonceError> {
+        if let Some(deadline) = deadline {
+            if deadline.is_expired(block) {
+                return Err(NonceError::DeadlineExpired { deadline });
+            }
+        }
+        let expected = self.current(storage, addr)?;
+        if nonce != expected {
+            return Err(NonceError::InvalidNonce {
+                expected,
+                received: nonce,
+            });
+        }
+        Ok(self.nonces.save(storage, addr, &(expected + 1))?)
+    }
+
+    /// Registers the key payloads of `addr` are signed with. Only call this on behalf
+    /// of `addr` itself, like `execute_set_public_key` does.
+    pub fn set_public_key(
+        &self,
+        storage: &mut dyn Storage,
+        addr: &Addr,
+        public_key: &PublicKey,
+    ) -> Result<(), NonceError> {
+        let valid = match public_key {
+            PublicKey::Secp256k1(key) => key.len() == 33 || key.len() == 65,
+            PublicKey::Ed25519(key) => key.len() == 32,
+        };
+        if !valid {
+            return Err(NonceError::InvalidPublicKey {});
+        }
+        Ok(self.keys.save(storage, addr, public_key)?)
+    }
+
+    pub fn public_key(&self, storage: &dyn Storage, addr: &Addr) -> StdResult<Option<PublicKey>> {
+        self.keys.may_load(storage, addr)
+    }
+
+    /// Checks the signature of a payload signed by `signer` with its registered key,
+    /// for this contract and chain, and consumes its nonce
+    pub fn use_signed<T: Serialize, Q: CustomQuery>(
+        &self,
+        deps: DepsMut<Q>,
+        env: &Env,
+        signer: &Addr,
+        signed: &SignedPayload<T>,
+        signature: &[u8],
+    ) -> Result<(), NonceError> {
+        let public_key =
+            self.public_key(deps.storage, signer)?
+                .ok_or_else(|| NonceError::NoPublicKey {
+                    signer: signer.clone(),
+                })?;
+        let message = signed.sign_bytes(&env.block.chain_id, &env.contract.address)?;
+        verify_signature(deps.api, &message, signature, &public_key)?;
+        self.use_nonce(
+            deps.storage,
+            signer,
+            signed.nonce,
+            signed.deadline,
+            &env.block,
+        )
+    }
+
+    /// The sender registers its own public key
+    pub fn execute_set_public_key<C, Q: CustomQuery>(
+        &self,
+        deps: DepsMut<Q>,
+        info: MessageInfo,
+        public_key: PublicKey,
+    ) -> Result<Response<C>, NonceError>
+    where
+        C: Clone + fmt::Debug + PartialEq + JsonSchema,
+    {
+        self.set_public_key(deps.storage, &info.sender, &public_key)?;
+
+        let attributes = vec![
+            attr("action", "set_public_key"),
+            attr("sender", info.sender),
+        ];
+        Ok(Response::new().add_attributes(attributes))
+    }
+
+    pub fn query_nonce<Q: CustomQuery>(
+        &self,
+        deps: Deps<Q>,
+        addr: &Addr,
+    ) -> StdResult<NonceResponse> {
+        let nonce = self.current(deps.storage, addr)?;
+        Ok(NonceResponse { nonce })
+    }
+
+    pub fn query_public_key<Q: CustomQuery>(
+        &self,
+        deps: Deps<Q>,
+        addr: &Addr,
+    ) -> StdResult<PublicKeyResponse> {
+        let public_key = self.public_key(deps.storage, addr)?;
+        Ok(PublicKeyResponse { public_key })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{message_info, mock_dependencies, mock_env};
+    use k256::ecdsa::signature::DigestSigner;
+
+    const NONCES: Nonces = Nonces::new("nonces", "keys");
+
+    #[cw_serde]
+    struct Transfer {
+        recipient: String,
+        amount: u64,
+    }
+
+    fn signed(nonce: u64, deadline: Option<Expiration>) -> SignedPayload<Transfer> {
+        SignedPayload {
+            payload: Transfer {
+                recipient: "bob".to_string(),
+                amount: 100,
+            },
+            nonce,
+            deadline,
+        }
+    }
+
+    fn secp256k1_sign(message: &[u8]) -> (Vec<u8>, PublicKey) {
+        let key = k256::ecdsa::SigningKey::from_bytes(&[7u8; 32].into()).unwrap();
+        let digest = Sha256::new_with_prefix(message);
+        let signature: k256::ecdsa::Signature = key.sign_digest(digest);
+        let public_key = key.verifying_key().to_encoded_point(true);
+        (
+            signature.to_bytes().to_vec(),
+            PublicKey::Secp256k1(public_key.as_bytes().into()),
+        )
+    }
+
+    fn ed25519_sign(message: &[u8]) -> (Vec<u8>, PublicKey) {
+        let key = ed25519_zebra::SigningKey::from([9u8; 32]);
+        let signature = key.sign(message);
+        let public_key = ed25519_zebra::VerificationKey::from(&key);
+        (
+            <[u8; 64]>::from(signature).to_vec(),
+            PublicKey::Ed25519(public_key.as_ref().into()),
+        )
+    }
+
+    /// Signs the payload for the contract and chain of `env`
+    fn sign_for(env: &Env, payload: &SignedPayload<Transfer>) -> (Vec<u8>, PublicKey) {
+        let message = payload
+            .sign_bytes(&env.block.chain_id, &env.contract.address)
+            .unwrap();
+        ed25519_sign(&message)
+    }
+
+    #[test]
+    fn nonces_increase_monotonically() {
+        let mut deps = mock_dependencies();
+        let block = mock_env().block;
+        let alice = Addr::unchecked("alice");
+        let bob = Addr::unchecked("bob");
+
+        assert_eq!(NONCES.current(&deps.storage, &alice).unwrap(), 0);
+        NONCES
+            .use_nonce(&mut deps.storage, &alice, 0, None, &block)
+            .unwrap();
+        NONCES
+            .use_nonce(&mut deps.storage, &alice, 1, None, &block)
+            .unwrap();
+
+        // replay
+        let err = NONCES
+            .use_nonce(&mut deps.storage, &alice, 1, None, &block)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            NonceError::InvalidNonce {
+                expected: 2,
+                received: 1
+            }
+        ));
+        // skipping ahead
+        let err = NONCES
+            .use_nonce(&mut deps.storage, &alice, 5, None, &block)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            NonceError::InvalidNonce {
+                expected: 2,
+                received: 5
+            }
+        ));
+
+        // other addresses are independent
+        let res = NONCES.query_nonce(deps.as_ref(), &bob).unwrap();
+        assert_eq!(res.nonce, 0);
+        let res = NONCES.query_nonce(deps.as_ref(), &alice).unwrap();
+        assert_eq!(res.nonce, 2);
+    }
+
+    #[test]
+    fn expired_deadline_is_rejected() {
+        let mut deps = mock_dependencies();
+        let block = mock_env().block;
+        let alice = Addr::unchecked("alice");
+
+        let deadline = Expiration::AtHeight(block.height);
+        let err = NONCES
+            .use_nonce(&mut deps.storage, &alice, 0, Some(deadline), &block)
+            .unwrap_err();
+        assert!(matches!(err, NonceError::DeadlineExpired { deadline: d } if d == deadline));
+        // the nonce was not consumed
+        assert_eq!(NONCES.current(&deps.storage, &alice).unwrap(), 0);
+
+        let deadline = Expiration::AtHeight(block.height + 1);
+        NONCES
+            .use_nonce(&mut deps.storage, &alice, 0, Some(deadline), &block)
+            .unwrap();
+    }
+
+    #[test]
+    fn verify_secp256k1_signature() {
+        let deps = mock_dependencies();
+        let (signature, public_key) = secp256k1_sign(b"payload");
+
+        verify_signature(&deps.api, b"payload", &signature, &public_key).unwrap();
+
+        // tampered message
+        let err = verify_signature(&deps.api, b"payloaf", &signature, &public_key).unwrap_err();
+        assert!(matches!(err, NonceError::InvalidSignature {}));
+
+        // malformed key
+        let err = verify_signature(
+            &deps.api,
+            b"payload",
+            &signature,
+            &PublicKey::Secp256k1(Binary::from(b"foo")),
+        )
+        .unwrap_err();
+        assert!(matches!(err, NonceError::Verification(_)));
+    }
+
+    #[test]
+    fn verify_ed25519_signature() {
+        let deps = mock_dependencies();
+        let (signature, public_key) = ed25519_sign(b"payload");
+
+        verify_signature(&deps.api, b"payload", &signature, &public_key).unwrap();
+
+        let err = verify_signature(&deps.api, b"payloaf", &signature, &public_key).unwrap_err();
+        assert!(matches!(err, NonceError::InvalidSignature {}));
+    }
+
+    #[test]
+    fn register_public_key() {
+        let mut deps = mock_dependencies();
+        let alice = Addr::unchecked("alice");
+        let (_, public_key) = secp256k1_sign(b"payload");
+
+        let res = NONCES.query_public_key(deps.as_ref(), &alice).unwrap();
+        assert_eq!(res.public_key, None);
+
+        let res: Response = NONCES
+            .execute_set_public_key(deps.as_mut(), message_info(&alice, &[]), public_key.clone())
+            .unwrap();
+        assert_eq!(res.attributes[0], attr("action", "set_public_key"));
+        let res = NONCES.query_public_key(deps.as_ref(), &alice).unwrap();
+        assert_eq!(res.public_key, Some(public_key));
+
+        let err = NONCES
+            .execute_set_public_key::<cosmwasm_std::Empty, _>(
+                deps.as_mut(),
+                message_info(&alice, &[]),
+                PublicKey::Ed25519(Binary::from(b"foo")),
+            )
+            .unwrap_err();
+        assert!(matches!(err, NonceError::InvalidPublicKey {}));
+    }
+
+    #[test]
+    fn signed_payload_cannot_be_replayed() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let alice = Addr::unchecked("alice");
+
+        let payload = signed(0, Some(Expiration::AtHeight(env.block.height + 100)));
+        let (signature, public_key) = sign_for(&env, &payload);
+
+        // the signer must have registered its key
+        let err = NONCES
+            .use_signed(deps.as_mut(), &env, &alice, &payload, &signature)
+            .unwrap_err();
+        assert!(matches!(err, NonceError::NoPublicKey { signer } if signer == alice));
+
+        NONCES
+            .set_public_key(&mut deps.storage, &alice, &public_key)
+            .unwrap();
+        NONCES
+            .use_signed(deps.as_mut(), &env, &alice, &payload, &signature)
+            .unwrap();
+        let err = NONCES
+            .use_signed(deps.as_mut(), &env, &alice, &payload, &signature)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            NonceError::InvalidNonce {
+                expected: 1,
+                received: 0
+            }
+        ));
+
+        // a valid signature by another key is not accepted for alice
+        let bob = Addr::unchecked("bob");
+        let (_, bob_key) = secp256k1_sign(b"bob");
+        NONCES
+            .set_public_key(&mut deps.storage, &bob, &bob_key)
+            .unwrap();
+        let err = NONCES
+            .use_signed(deps.as_mut(), &env, &bob, &payload, &signature)
+            .unwrap_err();
+        assert!(matches!(err, NonceError::InvalidSignature {}));
+    }
+
+    #[test]
+    fn sign_bytes_are_canonical() {
+        let payload = signed(3, Some(Expiration::AtHeight(5)));
+        let bytes = payload
+            .sign_bytes("chain-1", &Addr::unchecked("contract"))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            r#"{"chain_id":"chain-1","contract":"contract","deadline":{"at_height":5},"nonce":3,"payload":{"amount":100,"recipient":"bob"}}"#
+        );
+    }
+
+    #[test]
+    fn signature_is_bound_to_contract_and_chain() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let alice = Addr::unchecked("alice");
+
+        let payload = signed(0, None);
+        let mut other_contract = env.clone();
+        other_contract.contract.address = Addr::unchecked("other_contract");
+        let (signature, public_key) = sign_for(&other_contract, &payload);
+        NONCES
+            .set_public_key(&mut deps.storage, &alice, &public_key)
+            .unwrap();
+
+        // signed for another contract
+        let err = NONCES
+            .use_signed(deps.as_mut(), &env, &alice, &payload, &signature)
+            .unwrap_err();
+        assert!(matches!(err, NonceError::InvalidSignature {}));
+
+        // signed for another chain
+        let mut other_chain = env.clone();
+        other_chain.block.chain_id = "other-chain".to_string();
+        let (signature, _) = sign_for(&other_chain, &payload);
+        let err = NONCES
+            .use_signed(deps.as_mut(), &env, &alice, &payload, &signature)
+            .unwrap_err();
+        assert!(matches!(err, NonceError::InvalidSignature {}));
+
+        // the nonce was not consumed by the failed attempts
+        assert_eq!(NONCES.current(&deps.storage, &alice).unwrap(), 0);
+        let (signature, _) = sign_for(&env, &payload);
+        NONCES
+            .use_signed(deps.as_mut(), &env, &alice, &payload, &signature)
+            .unwrap();
+    }
+}
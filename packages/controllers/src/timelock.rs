@@ -0,0 +1,520 @@
+use schemars::JsonSchema;
+use std::cmp::Ordering;
+use std::fmt;
+use thiserror::Error;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, BlockInfo, CosmosMsg, CustomQuery, Deps, DepsMut, Empty, MessageInfo, Order, Response,
+    StdError, StdResult, Storage,
+};
+use cw_storage_plus::{Bound, Item, Map, Namespace};
+use cw_utils::{Duration, ExpirationError, Scheduled};
+
+use crate::admin::{Admin, AdminError};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum OperationStatus {
+    Queued,
+    Executed,
+    Cancelled,
+}
+
+impl fmt::Display for OperationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OperationStatus::Queued => write!(f, "queued"),
+            OperationStatus::Executed => write!(f, "executed"),
+            OperationStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// A set of messages that will be dispatched together once `execute_at` is reached
+#[cw_serde]
+pub struct Operation<C = Empty> {
+    pub id: u64,
+    pub msgs: Vec<CosmosMsg<C>>,
+    pub execute_at: Scheduled,
+    pub status: OperationStatus,
+}
+
+#[cw_serde]
+pub struct OperationsResponse<C = Empty> {
+    pub operations: Vec<Operation<C>>,
+}
+
+#[derive(Error, Debug)]
+pub enum TimelockError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Admin(#[from] AdminError),
+
+    #[error("{0}")]
+    Expiration(#[from] ExpirationError),
+
+    #[error("Cannot schedule by height with a time delay or by time with a height delay")]
+    DelayMismatch {},
+
+    #[error("Operation must not be scheduled before {earliest}")]
+    DelayTooShort { earliest: Scheduled },
+
+    #[error("Operation {id} does not exist")]
+    UnknownOperation { id: u64 },
+
+    #[error("Operation {id} is {status}, not queued")]
+    NotQueued { id: u64, status: OperationStatus },
+
+    #[error("Operation {id} is not ready before {execute_at}")]
+    NotReady { id: u64, execute_at: Scheduled },
+}
+
+/// Queues messages to be executed no earlier than a minimum delay in the future.
+/// Operations are never deleted, so their final status remains queryable.
+pub struct Timelock<C = Empty> {
+    min_delay: Item<Duration>,
+    counter: Item<u64>,
+    operations: Map<u64, Operation<C>>,
+}
+
+impl<C> Timelock<C>
+where
+    C: Clone + fmt::Debug + PartialEq + JsonSchema + serde::Serialize + serde::de::DeserializeOwned,
+{
+    pub const fn new(
+        min_delay_key: &'static str,
+        counter_key: &'static str,
+        operations_key: &'static str,
+    ) -> Self {
+        Timelock {
+            min_delay: Item::new(min_delay_key),
+            counter: Item::new(counter_key),
+            operations: Map::new(operations_key),
+        }
+    }
+
+    pub fn new_dyn(
+        min_delay_key: impl Into<Namespace>,
+        counter_key: impl Into<Namespace>,
+        operations_key: impl Into<Namespace>,
+    ) -> Self {
+        Timelock {
+            min_delay: Item::new_dyn(min_delay_key),
+            counter: Item::new_dyn(counter_key),
+            operations: Map::new_dyn(operations_key),
+        }
+    }
+
+    pub fn set_min_delay(&self, storage: &mut dyn Storage, min_delay: Duration) -> StdResult<()> {
+        self.min_delay.save(storage, &min_delay)
+    }
+
+    pub fn min_delay(&self, storage: &dyn Storage) -> StdResult<Duration> {
+        self.min_delay.load(storage)
+    }
+
+    /// Queues the messages for execution at `execute_at`, which must be at least
+    /// the minimum delay after the current block. Returns the id of the new operation.
+    pub fn schedule(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        msgs: Vec<CosmosMsg<C>>,
+        execute_at: Scheduled,
+    ) -> Result<u64, TimelockError> {
+        let min_delay = self.min_delay(storage)?;
+        let now = match min_delay {
+            Duration::Height(_) => Scheduled::AtHeight(block.height),
+            Duration::Time(_) => Scheduled::AtTime(block.time),
+        };
+        let earliest = now.checked_add(min_delay)?;
+        match execute_at.partial_cmp(&earliest) {
+            None => return Err(TimelockError::DelayMismatch {}),
+            Some(Ordering::Less) => return Err(TimelockError::DelayTooShort { earliest }),
+            _ => {}
+        }
+
+        let id = self.counter.may_load(storage)?.unwrap_or_default() + 1;
+        self.counter.save(storage, &id)?;
+        let operation = Operation {
+            id,
+            msgs,
+            execute_at,
+            status: OperationStatus::Queued,
+        };
+        self.operations.save(storage, id, &operation)?;
+        Ok(id)
+    }
+
+    /// Marks a queued operation as cancelled, so it can never be executed
+    pub fn cancel(&self, storage: &mut dyn Storage, id: u64) -> Result<(), TimelockError> {
+        let mut operation = self.load_queued(storage, id)?;
+        operation.status = OperationStatus::Cancelled;
+        Ok(self.operations.save(storage, id, &operation)?)
+    }
+
+    /// Marks a queued operation as executed if its time has come,
+    /// and returns the messages that must be dispatched
+    pub fn take_ready(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        id: u64,
+    ) -> Result<Vec<CosmosMsg<C>>, TimelockError> {
+        let mut operation = self.load_queued(storage, id)?;
+        if !operation.execute_at.is_triggered(block) {
+            return Err(TimelockError::NotReady {
+                id,
+                execute_at: operation.execute_at,
+            });
+        }
+        operation.status = OperationStatus::Executed;
+        self.operations.save(storage, id, &operation)?;
+        Ok(operation.msgs)
+    }
+
+    fn load_queued(&self, storage: &dyn Storage, id: u64) -> Result<Operation<C>, TimelockError> {
+        let operation = self
+            .operations
+            .may_load(storage, id)?
+            .ok_or(TimelockError::UnknownOperation { id })?;
+        if operation.status != OperationStatus::Queued {
+            return Err(TimelockError::NotQueued {
+                id,
+                status: operation.status,
+            });
+        }
+        Ok(operation)
+    }
+
+    pub fn execute_schedule<Q: CustomQuery>(
+        &self,
+        admin: &Admin,
+        deps: DepsMut<Q>,
+        info: MessageInfo,
+        block: &BlockInfo,
+        msgs: Vec<CosmosMsg<C>>,
+        execute_at: Scheduled,
+    ) -> Result<Response<C>, TimelockError> {
+        admin.assert_admin(deps.as_ref(), &info.sender)?;
+        let id = self.schedule(deps.storage, block, msgs, execute_at)?;
+
+        let attributes = vec![
+            attr("action", "schedule_operation"),
+            attr("operation_id", id.to_string()),
+            attr("execute_at", execute_at.to_string()),
+            attr("sender", info.sender),
+        ];
+        Ok(Response::new().add_attributes(attributes))
+    }
+
+    pub fn execute_cancel<Q: CustomQuery>(
+        &self,
+        admin: &Admin,
+        deps: DepsMut<Q>,
+        info: MessageInfo,
+        id: u64,
+    ) -> Result<Response<C>, TimelockError> {
+        admin.assert_admin(deps.as_ref(), &info.sender)?;
+        self.cancel(deps.storage, id)?;
+
+        let attributes = vec![
+            attr("action", "cancel_operation"),
+            attr("operation_id", id.to_string()),
+            attr("sender", info.sender),
+        ];
+        Ok(Response::new().add_attributes(attributes))
+    }
+
+    /// Anyone may trigger a queued operation once it is ready.
+    /// The stored messages are attached to the returned Response.
+    pub fn execute_ready<Q: CustomQuery>(
+        &self,
+        deps: DepsMut<Q>,
+        info: MessageInfo,
+        block: &BlockInfo,
+        id: u64,
+    ) -> Result<Response<C>, TimelockError> {
+        let msgs = self.take_ready(deps.storage, block, id)?;
+
+        let attributes = vec![
+            attr("action", "execute_operation"),
+            attr("operation_id", id.to_string()),
+            attr("sender", info.sender),
+        ];
+        Ok(Response::new()
+            .add_messages(msgs)
+            .add_attributes(attributes))
+    }
+
+    pub fn query_operation<Q: CustomQuery>(
+        &self,
+        deps: Deps<Q>,
+        id: u64,
+    ) -> StdResult<Option<Operation<C>>> {
+        self.operations.may_load(deps.storage, id)
+    }
+
+    /// Lists operations in ascending id order, regardless of their status
+    pub fn query_operations<Q: CustomQuery>(
+        &self,
+        deps: Deps<Q>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<OperationsResponse<C>> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+
+        let operations = self
+            .operations
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(_, op)| op))
+            .collect::<StdResult<_>>()?;
+        Ok(OperationsResponse { operations })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{message_info, mock_dependencies, mock_env};
+    use cosmwasm_std::{coins, Addr, BankMsg};
+
+    const TIMELOCK: Timelock = Timelock::new("min_delay", "op_count", "ops");
+
+    fn send_msg(amount: u128) -> CosmosMsg {
+        BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: coins(amount, "uatom"),
+        }
+        .into()
+    }
+
+    #[test]
+    fn schedule_respects_min_delay() {
+        let mut deps = mock_dependencies();
+        let block = mock_env().block;
+        TIMELOCK
+            .set_min_delay(&mut deps.storage, Duration::Height(100))
+            .unwrap();
+
+        let err = TIMELOCK
+            .schedule(
+                &mut deps.storage,
+                &block,
+                vec![send_msg(1)],
+                Scheduled::AtHeight(block.height + 99),
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            TimelockError::DelayTooShort { earliest } if earliest == Scheduled::AtHeight(block.height + 100)
+        ));
+
+        let err = TIMELOCK
+            .schedule(
+                &mut deps.storage,
+                &block,
+                vec![send_msg(1)],
+                Scheduled::AtTime(block.time.plus_days(7)),
+            )
+            .unwrap_err();
+        assert!(matches!(err, TimelockError::DelayMismatch {}));
+
+        let first = TIMELOCK
+            .schedule(
+                &mut deps.storage,
+                &block,
+                vec![send_msg(1)],
+                Scheduled::AtHeight(block.height + 100),
+            )
+            .unwrap();
+        let second = TIMELOCK
+            .schedule(
+                &mut deps.storage,
+                &block,
+                vec![send_msg(2)],
+                Scheduled::AtHeight(block.height + 500),
+            )
+            .unwrap();
+        assert_eq!((first, second), (1, 2));
+    }
+
+    #[test]
+    fn schedule_with_overflowing_delay() {
+        let mut deps = mock_dependencies();
+        let block = mock_env().block;
+        let timelock: Timelock = Timelock::new_dyn(
+            "min_delay".to_string(),
+            "op_count".to_string(),
+            "ops".to_string(),
+        );
+
+        for min_delay in [Duration::Height(u64::MAX), Duration::Time(u64::MAX)] {
+            timelock
+                .set_min_delay(&mut deps.storage, min_delay)
+                .unwrap();
+            let err = timelock
+                .schedule(
+                    &mut deps.storage,
+                    &block,
+                    vec![send_msg(1)],
+                    Scheduled::AtHeight(u64::MAX),
+                )
+                .unwrap_err();
+            assert!(matches!(
+                err,
+                TimelockError::Expiration(ExpirationError::Overflow {})
+            ));
+        }
+    }
+
+    #[test]
+    fn execute_ready_lifecycle() {
+        let mut deps = mock_dependencies();
+        let mut block = mock_env().block;
+        let anyone = Addr::unchecked("anyone");
+        TIMELOCK
+            .set_min_delay(&mut deps.storage, Duration::Time(3600))
+            .unwrap();
+
+        let execute_at = Scheduled::AtTime(block.time.plus_seconds(3600));
+        let id = TIMELOCK
+            .schedule(&mut deps.storage, &block, vec![send_msg(5)], execute_at)
+            .unwrap();
+
+        // too early
+        let err = TIMELOCK
+            .execute_ready(deps.as_mut(), message_info(&anyone, &[]), &block, id)
+            .unwrap_err();
+        assert!(matches!(err, TimelockError::NotReady { id: 1, .. }));
+
+        // ready now, and the messages are dispatched
+        block.time = block.time.plus_seconds(3600);
+        let res = TIMELOCK
+            .execute_ready(deps.as_mut(), message_info(&anyone, &[]), &block, id)
+            .unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].msg, send_msg(5));
+
+        // cannot be executed twice
+        let err = TIMELOCK
+            .execute_ready(deps.as_mut(), message_info(&anyone, &[]), &block, id)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            TimelockError::NotQueued {
+                id: 1,
+                status: OperationStatus::Executed
+            }
+        ));
+
+        let err = TIMELOCK
+            .execute_ready(deps.as_mut(), message_info(&anyone, &[]), &block, 7)
+            .unwrap_err();
+        assert!(matches!(err, TimelockError::UnknownOperation { id: 7 }));
+    }
+
+    #[test]
+    fn admin_schedules_and_cancels() {
+        let mut deps = mock_dependencies();
+        let block = mock_env().block;
+        let admin = Admin::new("admin");
+        let owner = Addr::unchecked("owner");
+        let imposter = Addr::unchecked("imposter");
+        admin.set(deps.as_mut(), Some(owner.clone())).unwrap();
+        TIMELOCK
+            .set_min_delay(&mut deps.storage, Duration::Height(10))
+            .unwrap();
+        let execute_at = Scheduled::AtHeight(block.height + 10);
+
+        let err = TIMELOCK
+            .execute_schedule(
+                &admin,
+                deps.as_mut(),
+                message_info(&imposter, &[]),
+                &block,
+                vec![send_msg(1)],
+                execute_at,
+            )
+            .unwrap_err();
+        assert!(matches!(err, TimelockError::Admin(AdminError::NotAdmin {})));
+
+        let res = TIMELOCK
+            .execute_schedule(
+                &admin,
+                deps.as_mut(),
+                message_info(&owner, &[]),
+                &block,
+                vec![send_msg(1)],
+                execute_at,
+            )
+            .unwrap();
+        assert_eq!(res.attributes[1], attr("operation_id", "1"));
+
+        let err = TIMELOCK
+            .execute_cancel(&admin, deps.as_mut(), message_info(&imposter, &[]), 1)
+            .unwrap_err();
+        assert!(matches!(err, TimelockError::Admin(AdminError::NotAdmin {})));
+
+        TIMELOCK
+            .execute_cancel(&admin, deps.as_mut(), message_info(&owner, &[]), 1)
+            .unwrap();
+        let op = TIMELOCK.query_operation(deps.as_ref(), 1).unwrap().unwrap();
+        assert_eq!(op.status, OperationStatus::Cancelled);
+
+        // a cancelled operation can never run
+        let mut later = block.clone();
+        later.height += 10;
+        let err = TIMELOCK
+            .take_ready(&mut deps.storage, &later, 1)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            TimelockError::NotQueued {
+                status: OperationStatus::Cancelled,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn query_operations_paginates() {
+        let mut deps = mock_dependencies();
+        let block = mock_env().block;
+        TIMELOCK
+            .set_min_delay(&mut deps.storage, Duration::Height(1))
+            .unwrap();
+        for i in 0..35 {
+            TIMELOCK
+                .schedule(
+                    &mut deps.storage,
+                    &block,
+                    vec![send_msg(i)],
+                    Scheduled::AtHeight(block.height + 1),
+                )
+                .unwrap();
+        }
+
+        let res = TIMELOCK
+            .query_operations(deps.as_ref(), None, None)
+            .unwrap();
+        assert_eq!(res.operations.len(), DEFAULT_LIMIT as usize);
+        assert_eq!(res.operations[0].id, 1);
+
+        let res = TIMELOCK
+            .query_operations(deps.as_ref(), Some(10), Some(100))
+            .unwrap();
+        assert_eq!(res.operations.len(), 25);
+        assert_eq!(res.operations[0].id, 11);
+        assert_eq!(res.operations[0].status, OperationStatus::Queued);
+    }
+}
@@ -0,0 +1,483 @@
+use schemars::JsonSchema;
+use std::fmt;
+use thiserror::Error;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    attr, Addr, BlockInfo, CosmosMsg, CustomQuery, Deps, DepsMut, MessageInfo, Order, Response,
+    StdError, StdResult, Storage, Uint128, Uint256,
+};
+use cw_storage_plus::{Bound, Map, Namespace};
+use cw_utils::{calc_range_start, Expiration, Scheduled, VestingError, VestingSchedule};
+
+use crate::admin::{Admin, AdminError};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+/// A grant vests linearly from `start` to `end`, but nothing can be claimed before `cliff`
+#[cw_serde]
+pub struct Grant {
+    pub total: Uint128,
+    pub claimed: Uint128,
+    pub start: Expiration,
+    pub cliff: Expiration,
+    pub end: Expiration,
+    /// Once revoked, `total` is reduced to what was vested at that point
+    pub revoked: bool,
+}
+
+impl Grant {
+    /// Returns the amount vested at the given block, including the claimed part
+    pub fn vested_at(&self, block: &BlockInfo) -> Uint128 {
+        if self.revoked {
+            return self.total;
+        }
+        let vested = match self.schedule() {
+            Ok(schedule) => schedule.vested_at(block, self.total.into()),
+            Err(_) => Uint256::zero(),
+        };
+        // never more than total
+        Uint128::try_from(vested).unwrap_or(self.total)
+    }
+
+    /// Returns the amount that can be claimed at the given block
+    pub fn claimable_at(&self, block: &BlockInfo) -> Uint128 {
+        self.vested_at(block).saturating_sub(self.claimed)
+    }
+
+    fn schedule(&self) -> Result<VestingSchedule, GrantError> {
+        let schedule = VestingSchedule::CliffLinear {
+            start: scheduled(self.start)?,
+            cliff: scheduled(self.cliff)?,
+            end: scheduled(self.end)?,
+        };
+        schedule.validate()?;
+        Ok(schedule)
+    }
+}
+
+fn scheduled(expiration: Expiration) -> Result<Scheduled, GrantError> {
+    match expiration {
+        Expiration::AtHeight(h) => Ok(Scheduled::AtHeight(h)),
+        Expiration::AtTime(t) => Ok(Scheduled::AtTime(t)),
+        Expiration::Never {} => Err(GrantError::NeverVests {}),
+        // a vesting curve needs a single unit
+        Expiration::AtHeightOrTime { .. } | Expiration::AtHeightAndTime { .. } => {
+            Err(VestingError::MixedUnits {}.into())
+        }
+    }
+}
+
+#[cw_serde]
+pub struct GrantResponse {
+    pub grant: Option<Grant>,
+}
+
+#[cw_serde]
+pub struct GrantInfo {
+    pub address: String,
+    pub grant: Grant,
+}
+
+#[cw_serde]
+pub struct GrantsResponse {
+    pub grants: Vec<GrantInfo>,
+}
+
+#[derive(Error, Debug)]
+pub enum GrantError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Admin(#[from] AdminError),
+
+    #[error("{0}")]
+    Schedule(#[from] VestingError),
+
+    #[error("Vesting points cannot be set to never")]
+    NeverVests {},
+
+    #[error("Grant amount cannot be zero")]
+    ZeroAmount {},
+
+    #[error("Address {addr} already has a grant")]
+    GrantExists { addr: String },
+
+    #[error("Address {addr} has no grant")]
+    NoGrant { addr: String },
+
+    #[error("Grant of {addr} was already revoked")]
+    AlreadyRevoked { addr: String },
+}
+
+// one grant per address
+pub struct Vesting(Map<&'static Addr, Grant>);
+
+impl Vesting {
+    pub const fn new(storage_key: &'static str) -> Self {
+        Vesting(Map::new(storage_key))
+    }
+
+    pub fn new_dyn(storage_key: impl Into<Namespace>) -> Self {
+        Vesting(Map::new_dyn(storage_key))
+    }
+
+    /// This creates a grant, such that the given address can claim the amount
+    /// linearly between `start` and `end`, once `cliff` is reached.
+    pub fn create_grant(
+        &self,
+        storage: &mut dyn Storage,
+        addr: &Addr,
+        amount: Uint128,
+        start: Expiration,
+        cliff: Expiration,
+        end: Expiration,
+    ) -> Result<(), GrantError> {
+        if amount.is_zero() {
+            return Err(GrantError::ZeroAmount {});
+        }
+        if self.0.has(storage, addr) {
+            return Err(GrantError::GrantExists {
+                addr: addr.to_string(),
+            });
+        }
+        let grant = Grant {
+            total: amount,
+            claimed: Uint128::zero(),
+            start,
+            cliff,
+            end,
+            revoked: false,
+        };
+        grant.schedule()?;
+        Ok(self.0.save(storage, addr, &grant)?)
+    }
+
+    /// This releases everything vested but not claimed yet and returns the amount to pay out.
+    /// Once the grant is fully claimed, it is removed.
+    pub fn claim_vested(
+        &self,
+        storage: &mut dyn Storage,
+        addr: &Addr,
+        block: &BlockInfo,
+    ) -> StdResult<Uint128> {
+        let mut grant = match self.0.may_load(storage, addr)? {
+            Some(grant) => grant,
+            None => return Ok(Uint128::zero()),
+        };
+        let to_send = grant.claimable_at(block);
+        grant.claimed += to_send;
+        if grant.claimed == grant.total {
+            self.0.remove(storage, addr);
+        } else {
+            self.0.save(storage, addr, &grant)?;
+        }
+        Ok(to_send)
+    }
+
+    /// This stops vesting for the given address and returns the unvested amount.
+    /// Whatever vested until now remains claimable.
+    pub fn revoke(
+        &self,
+        storage: &mut dyn Storage,
+        addr: &Addr,
+        block: &BlockInfo,
+    ) -> Result<Uint128, GrantError> {
+        let mut grant = self
+            .0
+            .may_load(storage, addr)?
+            .ok_or_else(|| GrantError::NoGrant {
+                addr: addr.to_string(),
+            })?;
+        if grant.revoked {
+            return Err(GrantError::AlreadyRevoked {
+                addr: addr.to_string(),
+            });
+        }
+        let vested = grant.vested_at(block);
+        let unvested = grant.total - vested;
+        grant.total = vested;
+        grant.revoked = true;
+        if grant.claimed == grant.total {
+            self.0.remove(storage, addr);
+        } else {
+            self.0.save(storage, addr, &grant)?;
+        }
+        Ok(unvested)
+    }
+
+    /// Revokes the grant and, if anything was unvested, attaches the message
+    /// returned by `refund` to send it back to the treasury
+    pub fn execute_revoke<C, Q: CustomQuery, F>(
+        &self,
+        admin: &Admin,
+        deps: DepsMut<Q>,
+        info: MessageInfo,
+        block: &BlockInfo,
+        addr: Addr,
+        refund: F,
+    ) -> Result<Response<C>, GrantError>
+    where
+        C: Clone + fmt::Debug + PartialEq + JsonSchema,
+        F: FnOnce(Uint128) -> StdResult<CosmosMsg<C>>,
+    {
+        admin.assert_admin(deps.as_ref(), &info.sender)?;
+        let unvested = self.revoke(deps.storage, &addr, block)?;
+
+        let attributes = vec![
+            attr("action", "revoke_grant"),
+            attr("grantee", addr),
+            attr("unvested", unvested),
+            attr("sender", info.sender),
+        ];
+        let mut res = Response::new().add_attributes(attributes);
+        if !unvested.is_zero() {
+            res = res.add_message(refund(unvested)?);
+        }
+        Ok(res)
+    }
+
+    pub fn query_grant<Q: CustomQuery>(
+        &self,
+        deps: Deps<Q>,
+        address: &Addr,
+    ) -> StdResult<GrantResponse> {
+        let grant = self.0.may_load(deps.storage, address)?;
+        Ok(GrantResponse { grant })
+    }
+
+    pub fn query_grants<Q: CustomQuery>(
+        &self,
+        deps: Deps<Q>,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    ) -> StdResult<GrantsResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = calc_range_start(start_after).map(Bound::ExclusiveRaw);
+
+        let grants = self
+            .0
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                let (addr, grant) = item?;
+                Ok(GrantInfo {
+                    address: addr.into(),
+                    grant,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+        Ok(GrantsResponse { grants })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use cosmwasm_std::testing::{message_info, mock_dependencies, mock_env};
+    use cosmwasm_std::{coins, BankMsg, Timestamp};
+
+    const VESTING: Vesting = Vesting::new("vesting");
+    const TEST_AMOUNT: u128 = 1000u128;
+
+    fn block_at(height: u64) -> BlockInfo {
+        let mut block = mock_env().block;
+        block.height = height;
+        block
+    }
+
+    fn create_test_grant(storage: &mut dyn Storage, addr: &Addr) {
+        VESTING
+            .create_grant(
+                storage,
+                addr,
+                TEST_AMOUNT.into(),
+                Expiration::AtHeight(100),
+                Expiration::AtHeight(150),
+                Expiration::AtHeight(200),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn create_grant_validates() {
+        let mut deps = mock_dependencies();
+        let addr = Addr::unchecked("addr");
+
+        let err = VESTING
+            .create_grant(
+                &mut deps.storage,
+                &addr,
+                TEST_AMOUNT.into(),
+                Expiration::AtHeight(100),
+                Expiration::AtHeight(150),
+                Expiration::AtTime(Timestamp::from_seconds(200)),
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            GrantError::Schedule(VestingError::MixedUnits {})
+        ));
+
+        let err = VESTING
+            .create_grant(
+                &mut deps.storage,
+                &addr,
+                TEST_AMOUNT.into(),
+                Expiration::AtHeight(100),
+                Expiration::AtHeight(150),
+                Expiration::Never {},
+            )
+            .unwrap_err();
+        assert!(matches!(err, GrantError::NeverVests {}));
+
+        let err = VESTING
+            .create_grant(
+                &mut deps.storage,
+                &addr,
+                Uint128::zero(),
+                Expiration::AtHeight(100),
+                Expiration::AtHeight(150),
+                Expiration::AtHeight(200),
+            )
+            .unwrap_err();
+        assert!(matches!(err, GrantError::ZeroAmount {}));
+
+        create_test_grant(&mut deps.storage, &addr);
+        let err = VESTING
+            .create_grant(
+                &mut deps.storage,
+                &addr,
+                TEST_AMOUNT.into(),
+                Expiration::AtHeight(100),
+                Expiration::AtHeight(150),
+                Expiration::AtHeight(200),
+            )
+            .unwrap_err();
+        assert!(matches!(err, GrantError::GrantExists { .. }));
+    }
+
+    #[test]
+    fn claim_vested_over_time() {
+        let mut deps = mock_dependencies();
+        let addr = Addr::unchecked("addr");
+        create_test_grant(&mut deps.storage, &addr);
+
+        let mut claim = |height| {
+            VESTING
+                .claim_vested(&mut deps.storage, &addr, &block_at(height))
+                .unwrap()
+        };
+        // before the cliff
+        assert_eq!(claim(120), Uint128::zero());
+        // at the cliff, half of it vested
+        assert_eq!(claim(150), Uint128::new(500));
+        assert_eq!(claim(150), Uint128::zero());
+        assert_eq!(claim(175), Uint128::new(250));
+        assert_eq!(claim(300), Uint128::new(250));
+
+        // fully claimed grants are removed
+        let res = VESTING.query_grant(deps.as_ref(), &addr).unwrap();
+        assert_eq!(res.grant, None);
+        let amount = VESTING
+            .claim_vested(&mut deps.storage, &addr, &block_at(400))
+            .unwrap();
+        assert_eq!(amount, Uint128::zero());
+    }
+
+    #[test]
+    fn revoke_keeps_vested_part() {
+        let mut deps = mock_dependencies();
+        let addr = Addr::unchecked("addr");
+        create_test_grant(&mut deps.storage, &addr);
+
+        let unvested = VESTING
+            .revoke(&mut deps.storage, &addr, &block_at(160))
+            .unwrap();
+        assert_eq!(unvested, Uint128::new(400));
+
+        let err = VESTING
+            .revoke(&mut deps.storage, &addr, &block_at(160))
+            .unwrap_err();
+        assert!(matches!(err, GrantError::AlreadyRevoked { .. }));
+
+        // vested part can still be claimed, nothing more
+        let amount = VESTING
+            .claim_vested(&mut deps.storage, &addr, &block_at(1000))
+            .unwrap();
+        assert_eq!(amount, Uint128::new(600));
+
+        let err = VESTING
+            .revoke(&mut deps.storage, &addr, &block_at(1000))
+            .unwrap_err();
+        assert!(matches!(err, GrantError::NoGrant { .. }));
+    }
+
+    #[test]
+    fn execute_revoke_refunds_treasury() {
+        let mut deps = mock_dependencies();
+        let admin = Admin::new("admin");
+        let owner = Addr::unchecked("owner");
+        let addr = Addr::unchecked("addr");
+        admin.set(deps.as_mut(), Some(owner.clone())).unwrap();
+        create_test_grant(&mut deps.storage, &addr);
+
+        let refund = |amount: Uint128| -> StdResult<CosmosMsg> {
+            Ok(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount: coins(amount.u128(), "ujuno"),
+            }
+            .into())
+        };
+
+        let err = VESTING
+            .execute_revoke(
+                &admin,
+                deps.as_mut(),
+                message_info(&addr, &[]),
+                &block_at(100),
+                addr.clone(),
+                refund,
+            )
+            .unwrap_err();
+        assert!(matches!(err, GrantError::Admin(AdminError::NotAdmin {})));
+
+        let res = VESTING
+            .execute_revoke(
+                &admin,
+                deps.as_mut(),
+                message_info(&owner, &[]),
+                &block_at(100),
+                addr.clone(),
+                refund,
+            )
+            .unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].msg, refund(Uint128::new(1000)).unwrap());
+        // nothing vested, so nothing is left
+        let res = VESTING.query_grant(deps.as_ref(), &addr).unwrap();
+        assert_eq!(res.grant, None);
+    }
+
+    #[test]
+    fn query_grants_paginates() {
+        let mut deps = mock_dependencies();
+        for i in 0..15 {
+            create_test_grant(&mut deps.storage, &Addr::unchecked(format!("addr{i:0>2}")));
+        }
+
+        let res = VESTING.query_grants(deps.as_ref(), None, None).unwrap();
+        assert_eq!(res.grants.len(), DEFAULT_LIMIT as usize);
+        assert_eq!(res.grants[0].address, "addr00");
+
+        let res = VESTING
+            .query_grants(deps.as_ref(), Some(Addr::unchecked("addr09")), None)
+            .unwrap();
+        assert_eq!(res.grants.len(), 5);
+        assert_eq!(res.grants[0].address, "addr10");
+        assert_eq!(res.grants[0].grant.total, Uint128::new(TEST_AMOUNT));
+    }
+}
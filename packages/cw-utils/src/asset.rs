@@ -0,0 +1,588 @@
+use std::{fmt, mem, ops};
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Addr, BankMsg, Coin, CosmosMsg, OverflowError, OverflowOperation, StdResult,
+    Uint128, Uint256, WasmMsg,
+};
+
+use crate::balance::is_valid_denom;
+use crate::{BalanceError, NativeBalance};
+
+/// AssetInfo identifies a token, either a native denom, including token-factory
+/// denoms like `factory/{creator}/{subdenom}`, or a cw20 contract.
+#[cw_serde]
+#[derive(Eq, PartialOrd, Ord, Hash)]
+pub enum AssetInfo {
+    Native(String),
+    Cw20(Addr),
+}
+
+/// Native denoms display as they are, cw20 tokens as `cw20:{contract}`
+impl fmt::Display for AssetInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetInfo::Native(denom) => write!(f, "{}", denom),
+            AssetInfo::Cw20(addr) => write!(f, "cw20:{}", addr),
+        }
+    }
+}
+
+/// An amount of some native or cw20 token
+#[cw_serde]
+pub struct Asset {
+    pub info: AssetInfo,
+    pub amount: Uint256,
+}
+
+impl Asset {
+    pub fn native(denom: impl Into<String>, amount: impl Into<Uint256>) -> Self {
+        Asset {
+            info: AssetInfo::Native(denom.into()),
+            amount: amount.into(),
+        }
+    }
+
+    pub fn cw20(contract: Addr, amount: impl Into<Uint256>) -> Self {
+        Asset {
+            info: AssetInfo::Cw20(contract),
+            amount: amount.into(),
+        }
+    }
+
+    /// Creates the message sending this asset from the contract to the recipient,
+    /// a `BankMsg::Send` for native tokens and a cw20 `Transfer` otherwise.
+    /// Fails for cw20 amounts above `Uint128::MAX`, which cw20 cannot transfer.
+    pub fn transfer_msg(&self, recipient: impl Into<String>) -> StdResult<CosmosMsg> {
+        match &self.info {
+            AssetInfo::Native(denom) => Ok(BankMsg::Send {
+                to_address: recipient.into(),
+                amount: vec![Coin::new(self.amount, denom)],
+            }
+            .into()),
+            AssetInfo::Cw20(contract) => cw20_transfer(contract, recipient.into(), self.amount),
+        }
+    }
+}
+
+impl From<Coin> for Asset {
+    fn from(coin: Coin) -> Self {
+        Asset::native(coin.denom, coin.amount)
+    }
+}
+
+/// The subset of the cw20 execute message needed to transfer tokens
+#[cw_serde]
+enum Cw20ExecuteMsg {
+    Transfer { recipient: String, amount: Uint128 },
+}
+
+fn cw20_transfer(contract: &Addr, recipient: String, amount: Uint256) -> StdResult<CosmosMsg> {
+    let amount = Uint128::try_from(amount)?;
+    Ok(WasmMsg::Execute {
+        contract_addr: contract.to_string(),
+        msg: to_json_binary(&Cw20ExecuteMsg::Transfer { recipient, amount })?,
+        funds: vec![],
+    }
+    .into())
+}
+
+// GenericBalance is the counterpart of NativeBalance holding native as well as cw20 tokens.
+// Assets are always sorted by `AssetInfo`, without duplicates or zero amounts.
+#[cw_serde]
+#[derive(Default)]
+#[serde(try_from = "Vec<Asset>")]
+#[schemaifier(mute_warnings)]
+pub struct GenericBalance(Vec<Asset>);
+
+impl GenericBalance {
+    /// Creates a normalized balance, merging duplicate assets and dropping zero amounts
+    pub fn new(assets: Vec<Asset>) -> Result<Self, BalanceError> {
+        assets
+            .into_iter()
+            .try_fold(GenericBalance::default(), GenericBalance::checked_add)
+    }
+
+    pub fn into_vec(self) -> Vec<Asset> {
+        self.0
+    }
+
+    /// The assets, sorted by `AssetInfo`, without duplicates or zero amounts
+    pub fn assets(&self) -> &[Asset] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// returns true if the balance has at least the required amount
+    pub fn has(&self, required: &Asset) -> bool {
+        self.amount_of(&required.info) >= required.amount
+    }
+
+    pub fn amount_of(&self, info: &AssetInfo) -> Uint256 {
+        self.find(info)
+            .map(|i| self.0[i].amount)
+            .unwrap_or_default()
+    }
+
+    /// The native part of the balance
+    pub fn native(&self) -> NativeBalance {
+        let coins = self
+            .0
+            .iter()
+            .filter_map(|asset| match &asset.info {
+                AssetInfo::Native(denom) => Some(Coin::new(asset.amount, denom)),
+                AssetInfo::Cw20(_) => None,
+            })
+            .collect();
+        // already sorted and merged
+        NativeBalance::new(coins).expect("normalized balance cannot overflow")
+    }
+
+    pub fn checked_add(mut self, other: Asset) -> Result<Self, BalanceError> {
+        if other.amount.is_zero() {
+            return Ok(self);
+        }
+        match self.find(&other.info) {
+            Ok(i) => {
+                self.0[i].amount = self.0[i].amount.checked_add(other.amount).map_err(|_| {
+                    BalanceError::Overflow {
+                        denom: other.info.to_string(),
+                    }
+                })?;
+            }
+            Err(i) => self.0.insert(i, other),
+        }
+        Ok(self)
+    }
+
+    pub fn checked_add_balance(self, other: &GenericBalance) -> Result<Self, BalanceError> {
+        other
+            .0
+            .iter()
+            .try_fold(self, |balance, asset| balance.checked_add(asset.clone()))
+    }
+
+    pub fn checked_sub(mut self, other: Asset) -> Result<Self, BalanceError> {
+        if other.amount.is_zero() {
+            return Ok(self);
+        }
+        let i = self
+            .find(&other.info)
+            .map_err(|_| BalanceError::MissingDenom {
+                denom: other.info.to_string(),
+                required: other.amount,
+            })?;
+        let available = self.0[i].amount;
+        if available < other.amount {
+            return Err(BalanceError::InsufficientFunds {
+                denom: other.info.to_string(),
+                available,
+                required: other.amount,
+            });
+        }
+        let remainder = available - other.amount;
+        if remainder.is_zero() {
+            self.0.remove(i);
+        } else {
+            self.0[i].amount = remainder;
+        }
+        Ok(self)
+    }
+
+    pub fn checked_sub_balance(self, other: &GenericBalance) -> Result<Self, BalanceError> {
+        other
+            .0
+            .iter()
+            .try_fold(self, |balance, asset| balance.checked_sub(asset.clone()))
+    }
+
+    /// similar to `GenericBalance.sub`, but doesn't fail when minuend less than subtrahend
+    pub fn sub_saturating(mut self, other: Asset) -> StdResult<Self> {
+        match self.find(&other.info) {
+            Ok(i) => {
+                if self.0[i].amount <= other.amount {
+                    self.0.remove(i);
+                } else {
+                    self.0[i].amount -= other.amount;
+                }
+            }
+            // error if no tokens
+            Err(_) => return Err(OverflowError::new(OverflowOperation::Sub).into()),
+        }
+        Ok(self)
+    }
+
+    /// Subtracts as much of every asset as available, ignoring missing assets
+    pub fn saturating_sub_balance(mut self, other: &GenericBalance) -> Self {
+        for asset in &other.0 {
+            if let Ok(i) = self.find(&asset.info) {
+                let remainder = self.0[i].amount.saturating_sub(asset.amount);
+                if remainder.is_zero() {
+                    self.0.remove(i);
+                } else {
+                    self.0[i].amount = remainder;
+                }
+            }
+        }
+        self
+    }
+
+    /// Creates the messages sending the whole balance to the recipient.
+    /// All native tokens go in one `BankMsg::Send`, followed by a cw20 `Transfer` per token.
+    /// Fails if a cw20 amount exceeds `Uint128::MAX`.
+    pub fn transfer_msgs(&self, recipient: impl Into<String>) -> StdResult<Vec<CosmosMsg>> {
+        let recipient = recipient.into();
+        let mut msgs = vec![];
+        let native = self.native();
+        if !native.is_empty() {
+            msgs.push(
+                BankMsg::Send {
+                    to_address: recipient.clone(),
+                    amount: native.into_vec(),
+                }
+                .into(),
+            );
+        }
+        for asset in &self.0 {
+            if let AssetInfo::Cw20(contract) = &asset.info {
+                msgs.push(cw20_transfer(contract, recipient.clone(), asset.amount)?);
+            }
+        }
+        Ok(msgs)
+    }
+
+    /// Index of the asset, or where to insert it to keep the assets sorted
+    fn find(&self, info: &AssetInfo) -> Result<usize, usize> {
+        self.0.binary_search_by(|asset| asset.info.cmp(info))
+    }
+}
+
+/// Validates native denoms, following the rules of the Cosmos SDK, and normalizes the assets
+impl TryFrom<Vec<Asset>> for GenericBalance {
+    type Error = BalanceError;
+
+    fn try_from(assets: Vec<Asset>) -> Result<Self, Self::Error> {
+        for asset in &assets {
+            if let AssetInfo::Native(denom) = &asset.info {
+                if !is_valid_denom(denom) {
+                    return Err(BalanceError::InvalidDenom {
+                        denom: denom.clone(),
+                    });
+                }
+            }
+        }
+        GenericBalance::new(assets)
+    }
+}
+
+impl From<NativeBalance> for GenericBalance {
+    fn from(balance: NativeBalance) -> Self {
+        // a normalized NativeBalance maps to normalized native assets
+        GenericBalance(balance.into_vec().into_iter().map(Asset::from).collect())
+    }
+}
+
+/// # Panics
+///
+/// Panics on overflow, use `GenericBalance::checked_add` to handle it
+impl ops::AddAssign<Asset> for GenericBalance {
+    fn add_assign(&mut self, other: Asset) {
+        *self = mem::take(self)
+            .checked_add(other)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+}
+
+impl ops::Add<Asset> for GenericBalance {
+    type Output = Self;
+
+    fn add(mut self, other: Asset) -> Self {
+        self += other;
+        self
+    }
+}
+
+impl ops::AddAssign<GenericBalance> for GenericBalance {
+    fn add_assign(&mut self, other: GenericBalance) {
+        for asset in other.0.into_iter() {
+            self.add_assign(asset);
+        }
+    }
+}
+
+impl ops::Add<GenericBalance> for GenericBalance {
+    type Output = Self;
+
+    fn add(mut self, other: GenericBalance) -> Self {
+        self += other;
+        self
+    }
+}
+
+impl ops::Sub<Asset> for GenericBalance {
+    type Output = StdResult<Self>;
+
+    fn sub(mut self, other: Asset) -> StdResult<Self> {
+        match self.find(&other.info) {
+            Ok(i) => {
+                let remainder = self.0[i].amount.checked_sub(other.amount)?;
+                if remainder.is_zero() {
+                    self.0.remove(i);
+                } else {
+                    self.0[i].amount = remainder;
+                }
+            }
+            // error if no tokens
+            Err(_) => return Err(OverflowError::new(OverflowOperation::Sub).into()),
+        }
+        Ok(self)
+    }
+}
+
+/// Amount first, like the coin strings `NativeBalance::from_str` parses,
+/// e.g. `"100uatom,75cw20:juno1..."`
+impl fmt::Display for GenericBalance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, asset) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}{}", asset.amount, asset.info)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::{coin, from_json};
+
+    fn token(name: &str) -> Addr {
+        Addr::unchecked(name)
+    }
+
+    #[test]
+    fn add_and_subtract() {
+        let balance = GenericBalance::new(vec![
+            Asset::cw20(token("cw20-b"), 50u128),
+            Asset::native("uatom", 100u128),
+            Asset::cw20(token("cw20-a"), 0u128),
+            Asset::cw20(token("cw20-b"), 25u128),
+        ])
+        .unwrap();
+        assert_eq!(
+            balance.assets(),
+            &[
+                Asset::native("uatom", 100u128),
+                Asset::cw20(token("cw20-b"), 75u128),
+            ]
+        );
+        assert!(balance.has(&Asset::cw20(token("cw20-b"), 75u128)));
+        assert!(!balance.has(&Asset::cw20(token("cw20-b"), 76u128)));
+        assert!(!balance.has(&Asset::cw20(token("cw20-a"), 1u128)));
+        assert_eq!(balance.to_string(), "100uatom,75cw20:cw20-b");
+
+        let balance = balance
+            .checked_add(Asset::cw20(token("cw20-a"), 10u128))
+            .unwrap()
+            .checked_sub(Asset::cw20(token("cw20-b"), 75u128))
+            .unwrap();
+        assert_eq!(
+            balance.assets(),
+            &[
+                Asset::native("uatom", 100u128),
+                Asset::cw20(token("cw20-a"), 10u128),
+            ]
+        );
+
+        let err = balance
+            .clone()
+            .checked_sub(Asset::cw20(token("cw20-a"), 11u128))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            BalanceError::InsufficientFunds {
+                denom: "cw20:cw20-a".to_string(),
+                available: Uint256::new(10),
+                required: Uint256::new(11),
+            }
+        );
+        let err = balance
+            .checked_sub(Asset::native("ujuno", 1u128))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            BalanceError::MissingDenom {
+                denom: "ujuno".to_string(),
+                required: Uint256::new(1),
+            }
+        );
+    }
+
+    #[test]
+    fn operators() {
+        let balance = GenericBalance::default()
+            + Asset::cw20(token("cw20"), 7u128)
+            + Asset::native("uatom", 0u128)
+            + Asset::native("uatom", 3u128);
+        assert_eq!(
+            balance.assets(),
+            &[
+                Asset::native("uatom", 3u128),
+                Asset::cw20(token("cw20"), 7u128),
+            ]
+        );
+
+        let mut sum = balance.clone() + balance.clone();
+        assert_eq!(
+            sum.amount_of(&AssetInfo::Cw20(token("cw20"))),
+            Uint256::new(14)
+        );
+        sum += Asset::cw20(token("cw20"), 1u128);
+        assert_eq!(
+            sum.amount_of(&AssetInfo::Cw20(token("cw20"))),
+            Uint256::new(15)
+        );
+
+        // subtract all of one asset, which should not leave a 0 amount
+        let no_cw20 = (balance.clone() - Asset::cw20(token("cw20"), 7u128)).unwrap();
+        assert_eq!(no_cw20.assets(), &[Asset::native("uatom", 3u128)]);
+        // subtract more than we have, or a missing asset
+        (balance.clone() - Asset::native("uatom", 4u128)).unwrap_err();
+        (balance.clone() - Asset::native("ujuno", 1u128)).unwrap_err();
+
+        let saturated = balance
+            .clone()
+            .sub_saturating(Asset::native("uatom", 4u128))
+            .unwrap();
+        assert_eq!(saturated.assets(), &[Asset::cw20(token("cw20"), 7u128)]);
+        balance
+            .clone()
+            .sub_saturating(Asset::native("ujuno", 1u128))
+            .unwrap_err();
+
+        let other = GenericBalance::new(vec![
+            Asset::cw20(token("cw20"), 2u128),
+            Asset::native("uatom", 5u128),
+            Asset::native("ujuno", 1u128),
+        ])
+        .unwrap();
+        assert_eq!(
+            balance.saturating_sub_balance(&other).assets(),
+            &[Asset::cw20(token("cw20"), 5u128)]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Overflow of uatom amount")]
+    fn add_overflow_panics() {
+        let _ = GenericBalance::default()
+            + Asset::native("uatom", Uint256::MAX)
+            + Asset::native("uatom", 1u128);
+    }
+
+    #[test]
+    fn combine_balances() {
+        let native = NativeBalance::new(vec![coin(5, "ujuno"), coin(3, "uatom")]).unwrap();
+        let balance = GenericBalance::from(native.clone());
+        assert_eq!(balance.native(), native);
+
+        let other = GenericBalance::new(vec![
+            Asset::cw20(token("cw20"), 7u128),
+            coin(2, "ujuno").into(),
+        ])
+        .unwrap();
+        let sum = balance.clone().checked_add_balance(&other).unwrap();
+        assert_eq!(
+            sum.amount_of(&AssetInfo::Native("ujuno".to_string())),
+            Uint256::new(7)
+        );
+        assert_eq!(
+            sum.amount_of(&AssetInfo::Cw20(token("cw20"))),
+            Uint256::new(7)
+        );
+        assert_eq!(sum.checked_sub_balance(&other).unwrap(), balance);
+    }
+
+    #[test]
+    fn transfer_messages() {
+        let balance = GenericBalance::new(vec![
+            Asset::cw20(token("cw20"), 7u128),
+            Asset::native("ujuno", 5u128),
+            Asset::native("uatom", 3u128),
+        ])
+        .unwrap();
+        let msgs = balance.transfer_msgs("bob").unwrap();
+        assert_eq!(
+            msgs,
+            vec![
+                BankMsg::Send {
+                    to_address: "bob".to_string(),
+                    amount: vec![coin(3, "uatom"), coin(5, "ujuno")],
+                }
+                .into(),
+                WasmMsg::Execute {
+                    contract_addr: "cw20".to_string(),
+                    msg: br#"{"transfer":{"recipient":"bob","amount":"7"}}"#.into(),
+                    funds: vec![],
+                }
+                .into(),
+            ]
+        );
+        assert_eq!(
+            Asset::native("uatom", 3u128).transfer_msg("bob").unwrap(),
+            BankMsg::Send {
+                to_address: "bob".to_string(),
+                amount: vec![coin(3, "uatom")],
+            }
+            .into()
+        );
+        assert_eq!(
+            Asset::cw20(token("cw20"), 7u128)
+                .transfer_msg("bob")
+                .unwrap(),
+            msgs[1]
+        );
+        assert!(GenericBalance::default()
+            .transfer_msgs("bob")
+            .unwrap()
+            .is_empty());
+
+        // cw20 amounts are limited to Uint128
+        let too_much = Uint256::from(Uint128::MAX) + Uint256::one();
+        Asset::cw20(token("cw20"), too_much)
+            .transfer_msg("bob")
+            .unwrap_err();
+        GenericBalance::new(vec![Asset::cw20(token("cw20"), too_much)])
+            .unwrap()
+            .transfer_msgs("bob")
+            .unwrap_err();
+        // native amounts are not
+        Asset::native("uatom", too_much)
+            .transfer_msg("bob")
+            .unwrap();
+    }
+
+    #[test]
+    fn deserialize_validates() {
+        let balance: GenericBalance = from_json(
+            r#"[{"info":{"cw20":"cw20"},"amount":"7"},{"info":{"native":"uatom"},"amount":"3"},{"info":{"cw20":"cw20"},"amount":"1"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            balance.assets(),
+            &[
+                Asset::native("uatom", 3u128),
+                Asset::cw20(token("cw20"), 8u128),
+            ]
+        );
+
+        let err =
+            from_json::<GenericBalance>(r#"[{"info":{"native":"1a"},"amount":"5"}]"#).unwrap_err();
+        assert!(err.to_string().contains("Invalid denom '1a'"), "{err}");
+    }
+}
@@ -1,19 +1,68 @@
-use std::{fmt, ops};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::{fmt, mem, ops};
 
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Coin, OverflowError, OverflowOperation, StdResult};
+use cosmwasm_std::{BankMsg, Coin, Decimal, OverflowError, OverflowOperation, StdResult, Uint256};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum BalanceError {
+    #[error("No {denom} in balance, {required} required")]
+    MissingDenom { denom: String, required: Uint256 },
+
+    #[error("Insufficient {denom}: {available} available, {required} required")]
+    InsufficientFunds {
+        denom: String,
+        available: Uint256,
+        required: Uint256,
+    },
+
+    #[error("Overflow of {denom} amount")]
+    Overflow { denom: String },
+
+    #[error("Cannot split by a total weight of zero")]
+    ZeroTotalWeight {},
+
+    #[error("Total weight overflows")]
+    WeightOverflow {},
+
+    #[error("Split ratios must add up to 1")]
+    InvalidRatios {},
+
+    #[error("Invalid denom '{denom}'")]
+    InvalidDenom { denom: String },
+
+    #[error("Invalid coin '{coin}', expected an amount followed by a denom")]
+    InvalidCoin { coin: String },
+}
 
 // Balance wraps Vec<Coin> and provides some nice helpers. It mutates the Vec and can be
 // unwrapped when done.
+// The coins are always normalized, see `normalize`, which is also enforced when deserializing.
 #[cw_serde]
 #[derive(Default)]
-pub struct NativeBalance(pub Vec<Coin>);
+#[serde(try_from = "Vec<Coin>")]
+#[schemaifier(mute_warnings)]
+pub struct NativeBalance(Vec<Coin>);
 
 impl NativeBalance {
+    /// Creates a normalized balance, merging duplicate denoms and dropping zero amounts
+    pub fn new(coins: Vec<Coin>) -> Result<Self, BalanceError> {
+        coins
+            .into_iter()
+            .try_fold(NativeBalance::default(), NativeBalance::checked_add)
+    }
+
     pub fn into_vec(self) -> Vec<Coin> {
         self.0
     }
 
+    /// The coins, sorted by denom, without duplicates or zero amounts
+    pub fn coins(&self) -> &[Coin] {
+        &self.0
+    }
+
     /// returns true if the list of coins has at least the required amount
     pub fn has(&self, required: &Coin) -> bool {
         self.0
@@ -24,33 +73,13 @@ impl NativeBalance {
     }
 
     /// normalize Wallet (sorted by denom, no 0 elements, no duplicate denoms)
+    ///
+    /// # Panics
+    ///
+    /// Panics if the amounts of a duplicate denom overflow when merged,
+    /// use `NativeBalance::new` to handle that
     pub fn normalize(&mut self) {
-        // drop 0's
-        self.0.retain(|c| !c.amount.is_zero());
-        // sort
-        self.0.sort_unstable_by(|a, b| a.denom.cmp(&b.denom));
-
-        // find all i where (self[i-1].denom == self[i].denom).
-        let mut dups: Vec<usize> = self
-            .0
-            .iter()
-            .enumerate()
-            .filter_map(|(i, c)| {
-                if i != 0 && c.denom == self.0[i - 1].denom {
-                    Some(i)
-                } else {
-                    None
-                }
-            })
-            .collect();
-        dups.reverse();
-
-        // we go through the dups in reverse order (to avoid shifting indexes of other ones)
-        for dup in dups {
-            let add = self.0[dup].amount;
-            self.0[dup - 1].amount += add;
-            self.0.remove(dup);
-        }
+        *self = NativeBalance::new(mem::take(&mut self.0)).unwrap_or_else(|err| panic!("{err}"));
     }
 
     fn find(&self, denom: &str) -> Option<(usize, &Coin)> {
@@ -68,6 +97,149 @@ impl NativeBalance {
         !self.0.iter().any(|x| !x.amount.is_zero())
     }
 
+    /// Total amount of the denom, summing up duplicate entries
+    pub fn amount_of(&self, denom: &str) -> Uint256 {
+        self.0
+            .iter()
+            .filter(|c| c.denom == denom)
+            .map(|c| c.amount)
+            .fold(Uint256::zero(), Uint256::saturating_add)
+    }
+
+    /// Iterates over the total amount per denom, sorted by denom and without zero amounts.
+    /// Duplicate entries are summed up, so this does not depend on `normalize`.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Uint256)> {
+        self.totals().into_iter()
+    }
+
+    /// returns true if this holds at least every amount of `other`
+    pub fn covers(&self, other: &NativeBalance) -> bool {
+        let totals = self.totals();
+        other
+            .iter()
+            .all(|(denom, amount)| totals.get(denom).is_some_and(|have| *have >= amount))
+    }
+
+    /// What remains of this after taking away `other`, per denom, never below zero
+    pub fn difference(&self, other: &NativeBalance) -> NativeBalance {
+        let other = other.totals();
+        self.merge(&other, |amount, other| amount.saturating_sub(other))
+    }
+
+    /// The amounts held by both, i.e. the smaller amount for every denom
+    pub fn intersection(&self, other: &NativeBalance) -> NativeBalance {
+        self.min(other)
+    }
+
+    /// The smaller amount for every denom, a missing denom counting as zero
+    pub fn min(&self, other: &NativeBalance) -> NativeBalance {
+        let other = other.totals();
+        self.merge(&other, Uint256::min)
+    }
+
+    /// The larger amount for every denom, a missing denom counting as zero
+    pub fn max(&self, other: &NativeBalance) -> NativeBalance {
+        let mut totals = self.totals();
+        for (denom, amount) in other.iter() {
+            let entry = totals.entry(denom).or_default();
+            *entry = (*entry).max(amount);
+        }
+        from_totals(totals)
+    }
+
+    /// Splits the balance between recipients proportional to their weights. Every share is
+    /// rounded down, and the rounding dust of each denom goes to the first recipient with
+    /// a non-zero weight, so
+    /// the shares always add up to this balance exactly. Shares are normalized and may be empty.
+    pub fn split_by_weights<T: Clone>(
+        &self,
+        weights: &[(T, Uint256)],
+    ) -> Result<Vec<(T, NativeBalance)>, BalanceError> {
+        let total_weight = weights
+            .iter()
+            .try_fold(Uint256::zero(), |sum, (_, weight)| sum.checked_add(*weight))
+            .map_err(|_| BalanceError::WeightOverflow {})?;
+        if total_weight.is_zero() {
+            return Err(BalanceError::ZeroTotalWeight {});
+        }
+
+        let first = weights
+            .iter()
+            .position(|(_, weight)| !weight.is_zero())
+            .unwrap_or_default();
+        let mut shares: Vec<_> = weights
+            .iter()
+            .map(|(recipient, _)| (recipient.clone(), NativeBalance::default()))
+            .collect();
+        for (denom, amount) in self.iter() {
+            let mut dust = amount;
+            for ((_, share), (_, weight)) in shares.iter_mut().zip(weights) {
+                let part = amount.multiply_ratio(*weight, total_weight);
+                dust -= part;
+                if !part.is_zero() {
+                    share.0.push(Coin::new(part, denom));
+                }
+            }
+            if !dust.is_zero() {
+                let first = &mut shares[first].1;
+                match first.0.last_mut() {
+                    Some(last) if last.denom == denom => last.amount += dust,
+                    _ => first.0.push(Coin::new(dust, denom)),
+                }
+            }
+        }
+        Ok(shares)
+    }
+
+    /// Splits the balance by ratios, which must add up to 1.
+    /// Rounding works as in `split_by_weights`.
+    pub fn split_by_ratios(&self, ratios: &[Decimal]) -> Result<Vec<NativeBalance>, BalanceError> {
+        let sum = ratios
+            .iter()
+            .try_fold(Decimal::zero(), |sum, ratio| sum.checked_add(*ratio))
+            .map_err(|_| BalanceError::InvalidRatios {})?;
+        if sum != Decimal::one() {
+            return Err(BalanceError::InvalidRatios {});
+        }
+        let weights: Vec<_> = ratios
+            .iter()
+            .map(|ratio| ((), Uint256::from(ratio.atomics())))
+            .collect();
+        let shares = self.split_by_weights(&weights)?;
+        Ok(shares.into_iter().map(|(_, share)| share).collect())
+    }
+
+    /// Applies `f` to the amounts of every denom in either balance, returning a
+    /// normalized result
+    fn merge(
+        &self,
+        other: &BTreeMap<&str, Uint256>,
+        f: impl Fn(Uint256, Uint256) -> Uint256,
+    ) -> NativeBalance {
+        let mut totals = self.totals();
+        for denom in other.keys() {
+            totals.entry(denom).or_default();
+        }
+        let merged = totals
+            .into_iter()
+            .map(|(denom, amount)| {
+                let other = other.get(denom).copied().unwrap_or_default();
+                (denom, f(amount, other))
+            })
+            .collect();
+        from_totals(merged)
+    }
+
+    /// Total amount per denom, without zero amounts
+    fn totals(&self) -> BTreeMap<&str, Uint256> {
+        let mut totals = BTreeMap::new();
+        for coin in self.0.iter().filter(|c| !c.amount.is_zero()) {
+            let entry: &mut Uint256 = totals.entry(coin.denom.as_str()).or_default();
+            *entry = entry.saturating_add(coin.amount);
+        }
+        totals
+    }
+
     /// similar to `Balance.sub`, but doesn't fail when minuend less than subtrahend
     pub fn sub_saturating(mut self, other: Coin) -> StdResult<Self> {
         match self.find(&other.denom) {
@@ -83,6 +255,178 @@ impl NativeBalance {
         };
         Ok(self)
     }
+
+    /// Adds the coin, failing instead of panicking on overflow
+    pub fn checked_add(mut self, other: Coin) -> Result<Self, BalanceError> {
+        if other.amount.is_zero() {
+            return Ok(self);
+        }
+        match self.find(&other.denom) {
+            Some((i, c)) => {
+                self.0[i].amount =
+                    c.amount
+                        .checked_add(other.amount)
+                        .map_err(|_| BalanceError::Overflow {
+                            denom: other.denom.clone(),
+                        })?;
+            }
+            None => match self.insert_pos(&other.denom) {
+                Some(idx) => self.0.insert(idx, other),
+                None => self.0.push(other),
+            },
+        };
+        Ok(self)
+    }
+
+    pub fn checked_add_balance(self, other: &NativeBalance) -> Result<Self, BalanceError> {
+        other
+            .0
+            .iter()
+            .try_fold(self, |balance, coin| balance.checked_add(coin.clone()))
+    }
+
+    /// Subtracts the coin, with errors telling a missing denom from an insufficient amount
+    pub fn checked_sub(mut self, other: Coin) -> Result<Self, BalanceError> {
+        if other.amount.is_zero() {
+            return Ok(self);
+        }
+        match self.find(&other.denom) {
+            Some((i, c)) => {
+                if c.amount < other.amount {
+                    return Err(BalanceError::InsufficientFunds {
+                        denom: other.denom,
+                        available: c.amount,
+                        required: other.amount,
+                    });
+                }
+                let remainder = c.amount - other.amount;
+                if remainder.is_zero() {
+                    self.0.remove(i);
+                } else {
+                    self.0[i].amount = remainder;
+                }
+            }
+            None => {
+                return Err(BalanceError::MissingDenom {
+                    denom: other.denom,
+                    required: other.amount,
+                })
+            }
+        };
+        Ok(self)
+    }
+
+    pub fn checked_sub_balance(self, other: &NativeBalance) -> Result<Self, BalanceError> {
+        other
+            .0
+            .iter()
+            .try_fold(self, |balance, coin| balance.checked_sub(coin.clone()))
+    }
+
+    /// Subtracts as much of every coin as available, ignoring missing denoms
+    pub fn saturating_sub_balance(mut self, other: &NativeBalance) -> Self {
+        for coin in &other.0 {
+            if let Some((i, c)) = self.find(&coin.denom) {
+                let remainder = c.amount.saturating_sub(coin.amount);
+                if remainder.is_zero() {
+                    self.0.remove(i);
+                } else {
+                    self.0[i].amount = remainder;
+                }
+            }
+        }
+        self
+    }
+
+    /// Multiplies every amount with the ratio, rounding down.
+    /// Coins that end up with zero amount are removed.
+    pub fn checked_mul_ratio(mut self, ratio: Decimal) -> Result<Self, BalanceError> {
+        for coin in self.0.iter_mut() {
+            coin.amount =
+                coin.amount
+                    .checked_mul_floor(ratio)
+                    .map_err(|_| BalanceError::Overflow {
+                        denom: coin.denom.clone(),
+                    })?;
+        }
+        self.0.retain(|c| !c.amount.is_zero());
+        Ok(self)
+    }
+}
+
+/// Creates a `BankMsg::Send` for every recipient, skipping empty balances.
+/// Use it with the result of `NativeBalance::split_by_weights`.
+pub fn bank_sends<T: Into<String>>(
+    shares: impl IntoIterator<Item = (T, NativeBalance)>,
+) -> Vec<BankMsg> {
+    shares
+        .into_iter()
+        .filter(|(_, share)| !share.is_empty())
+        .map(|(recipient, share)| BankMsg::Send {
+            to_address: recipient.into(),
+            amount: share.into_vec(),
+        })
+        .collect()
+}
+
+fn from_totals(totals: BTreeMap<&str, Uint256>) -> NativeBalance {
+    NativeBalance(
+        totals
+            .into_iter()
+            .filter(|(_, amount)| !amount.is_zero())
+            .map(|(denom, amount)| Coin::new(amount, denom))
+            .collect(),
+    )
+}
+
+/// Validates the denoms, following the rules of the Cosmos SDK, and normalizes the coins
+impl TryFrom<Vec<Coin>> for NativeBalance {
+    type Error = BalanceError;
+
+    fn try_from(coins: Vec<Coin>) -> Result<Self, Self::Error> {
+        if let Some(coin) = coins.iter().find(|c| !is_valid_denom(&c.denom)) {
+            return Err(BalanceError::InvalidDenom {
+                denom: coin.denom.clone(),
+            });
+        }
+        NativeBalance::new(coins)
+    }
+}
+
+/// Parses the coin-string format of the Cosmos SDK, like `"100uatom,5ujuno"`
+impl FromStr for NativeBalance {
+    type Err = BalanceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let coins = s
+            .split(',')
+            .map(str::trim)
+            .filter(|coin| !coin.is_empty())
+            .map(|coin| {
+                let invalid = || BalanceError::InvalidCoin {
+                    coin: coin.to_string(),
+                };
+                let digits = coin
+                    .find(|c: char| !c.is_ascii_digit())
+                    .ok_or_else(invalid)?;
+                if digits == 0 {
+                    return Err(invalid());
+                }
+                let amount: Uint256 = coin[..digits].parse().map_err(|_| invalid())?;
+                Ok(Coin::new(amount, coin[digits..].trim()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        NativeBalance::try_from(coins)
+    }
+}
+
+/// Denoms have 3 to 128 characters, starting with a letter, followed by
+/// letters, digits or one of `/:._-`. That is `[a-zA-Z][a-zA-Z0-9/:._-]{2,127}`.
+pub(crate) fn is_valid_denom(denom: &str) -> bool {
+    let mut chars = denom.chars();
+    (3..=128).contains(&denom.len())
+        && chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || "/:._-".contains(c))
 }
 
 impl fmt::Display for NativeBalance {
@@ -94,18 +438,14 @@ impl fmt::Display for NativeBalance {
     }
 }
 
+/// # Panics
+///
+/// Panics on overflow, use `NativeBalance::checked_add` to handle it
 impl ops::AddAssign<Coin> for NativeBalance {
     fn add_assign(&mut self, other: Coin) {
-        match self.find(&other.denom) {
-            Some((i, c)) => {
-                self.0[i].amount = c.amount + other.amount;
-            }
-            // place this in proper sorted order
-            None => match self.insert_pos(&other.denom) {
-                Some(idx) => self.0.insert(idx, other),
-                None => self.0.push(other),
-            },
-        };
+        *self = mem::take(self)
+            .checked_add(other)
+            .unwrap_or_else(|err| panic!("{err}"));
     }
 }
 
@@ -283,6 +623,424 @@ mod test {
         assert!(missing.is_err());
     }
 
+    #[test]
+    #[should_panic(expected = "Overflow of ETH amount")]
+    fn addition_overflow_panics() {
+        let mut balance = NativeBalance(vec![coin(555, "BTC")]);
+        balance += Coin::new(Uint256::MAX, "ETH");
+        balance += coin(1, "ETH");
+    }
+
+    #[test]
+    #[should_panic(expected = "Overflow of ETH amount")]
+    fn normalize_overflow_panics() {
+        let mut balance = NativeBalance(vec![Coin::new(Uint256::MAX, "ETH"), coin(1, "ETH")]);
+        balance.normalize();
+    }
+
+    #[test]
+    fn checked_addition() {
+        let balance = NativeBalance(vec![coin(555, "BTC"), coin(12345, "ETH")]);
+
+        let sum = balance
+            .clone()
+            .checked_add_balance(&NativeBalance(vec![coin(1, "ATOM"), coin(45, "BTC")]))
+            .unwrap();
+        assert_eq!(
+            sum,
+            NativeBalance(vec![coin(1, "ATOM"), coin(600, "BTC"), coin(12345, "ETH")])
+        );
+        // zero amounts are not added
+        let same = balance.clone().checked_add(coin(0, "ATOM")).unwrap();
+        assert_eq!(same, balance);
+
+        let max = Coin::new(Uint256::MAX, "BTC");
+        let err = balance.checked_add(max).unwrap_err();
+        assert_eq!(
+            err,
+            BalanceError::Overflow {
+                denom: "BTC".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn checked_subtraction() {
+        let balance = NativeBalance(vec![coin(555, "BTC"), coin(12345, "ETH")]);
+
+        let rest = balance
+            .clone()
+            .checked_sub_balance(&NativeBalance(vec![coin(555, "BTC"), coin(345, "ETH")]))
+            .unwrap();
+        assert_eq!(rest, NativeBalance(vec![coin(12000, "ETH")]));
+
+        let err = balance.clone().checked_sub(coin(556, "BTC")).unwrap_err();
+        assert_eq!(
+            err,
+            BalanceError::InsufficientFunds {
+                denom: "BTC".to_string(),
+                available: Uint256::new(555),
+                required: Uint256::new(556),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Insufficient BTC: 555 available, 556 required"
+        );
+
+        let err = balance
+            .clone()
+            .checked_sub_balance(&NativeBalance(vec![coin(5, "ETH"), coin(1, "ATOM")]))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            BalanceError::MissingDenom {
+                denom: "ATOM".to_string(),
+                required: Uint256::new(1),
+            }
+        );
+
+        let rest = balance.saturating_sub_balance(&NativeBalance(vec![
+            coin(1, "ATOM"),
+            coin(600, "BTC"),
+            coin(45, "ETH"),
+        ]));
+        assert_eq!(rest, NativeBalance(vec![coin(12300, "ETH")]));
+    }
+
+    #[test]
+    fn multiply_by_ratio() {
+        let balance = NativeBalance(vec![coin(3, "BTC"), coin(12345, "ETH")]);
+
+        let third = balance
+            .clone()
+            .checked_mul_ratio(Decimal::percent(30))
+            .unwrap();
+        // rounds down, dropping zero amounts
+        assert_eq!(third, NativeBalance(vec![coin(3703, "ETH")]));
+        let double = balance.checked_mul_ratio(Decimal::percent(200)).unwrap();
+        assert_eq!(
+            double,
+            NativeBalance(vec![coin(6, "BTC"), coin(24690, "ETH")])
+        );
+
+        let err = NativeBalance(vec![Coin::new(Uint256::MAX, "BTC")])
+            .checked_mul_ratio(Decimal::percent(200))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            BalanceError::Overflow {
+                denom: "BTC".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn compare_balances() {
+        // not normalized on purpose
+        let balance = NativeBalance(vec![
+            coin(100, "ETH"),
+            coin(0, "ATOM"),
+            coin(555, "BTC"),
+            coin(23, "ETH"),
+        ]);
+        assert_eq!(balance.amount_of("ETH"), Uint256::new(123));
+        assert_eq!(balance.amount_of("ATOM"), Uint256::zero());
+        assert_eq!(
+            balance.iter().collect::<Vec<_>>(),
+            vec![("BTC", Uint256::new(555)), ("ETH", Uint256::new(123))]
+        );
+
+        let other = NativeBalance(vec![coin(120, "ETH"), coin(7, "ATOM")]);
+        assert!(!balance.covers(&other));
+        assert!(balance.covers(&NativeBalance(vec![coin(120, "ETH"), coin(0, "ATOM")])));
+        assert!(balance.covers(&NativeBalance::default()));
+
+        assert_eq!(
+            balance.difference(&other),
+            NativeBalance(vec![coin(555, "BTC"), coin(3, "ETH")])
+        );
+        assert_eq!(
+            balance.intersection(&other),
+            NativeBalance(vec![coin(120, "ETH")])
+        );
+        assert_eq!(balance.min(&other), balance.intersection(&other));
+        assert_eq!(
+            balance.max(&other),
+            NativeBalance(vec![coin(7, "ATOM"), coin(555, "BTC"), coin(123, "ETH")])
+        );
+        assert!(balance.difference(&balance).is_empty());
+    }
+
+    #[test]
+    fn split_by_weights_assigns_dust_to_first() {
+        let balance = NativeBalance(vec![coin(100, "ATOM"), coin(10, "BTC"), coin(1, "ETH")]);
+        let shares = balance
+            .split_by_weights(&[
+                ("alice", Uint256::new(1)),
+                ("bob", Uint256::new(1)),
+                ("carl", Uint256::new(1)),
+            ])
+            .unwrap();
+        assert_eq!(
+            shares,
+            vec![
+                (
+                    "alice",
+                    NativeBalance(vec![coin(34, "ATOM"), coin(4, "BTC"), coin(1, "ETH")])
+                ),
+                ("bob", NativeBalance(vec![coin(33, "ATOM"), coin(3, "BTC")])),
+                (
+                    "carl",
+                    NativeBalance(vec![coin(33, "ATOM"), coin(3, "BTC")])
+                ),
+            ]
+        );
+
+        let msgs = bank_sends(shares);
+        assert_eq!(msgs.len(), 3);
+        assert_eq!(
+            msgs[1],
+            BankMsg::Send {
+                to_address: "bob".to_string(),
+                amount: vec![coin(33, "ATOM"), coin(3, "BTC")],
+            }
+        );
+
+        // recipients without weight receive nothing, and get no message
+        let shares = balance
+            .split_by_weights(&[
+                ("alice", Uint256::zero()),
+                ("bob", Uint256::new(2)),
+                ("carl", Uint256::new(1)),
+            ])
+            .unwrap();
+        assert!(shares[0].1.is_empty());
+        assert_eq!(
+            shares[1].1,
+            NativeBalance(vec![coin(67, "ATOM"), coin(7, "BTC"), coin(1, "ETH")])
+        );
+        assert_eq!(bank_sends(shares).len(), 2);
+
+        let err = balance
+            .split_by_weights(&[("alice", Uint256::zero())])
+            .unwrap_err();
+        assert_eq!(err, BalanceError::ZeroTotalWeight {});
+        let err = balance.split_by_weights::<&str>(&[]).unwrap_err();
+        assert_eq!(err, BalanceError::ZeroTotalWeight {});
+        let err = balance
+            .split_by_weights(&[("alice", Uint256::MAX), ("bob", Uint256::one())])
+            .unwrap_err();
+        assert_eq!(err, BalanceError::WeightOverflow {});
+    }
+
+    #[test]
+    fn split_by_ratios_adds_up() {
+        let balance = NativeBalance(vec![coin(1001, "ATOM"), coin(7, "BTC")]);
+        let shares = balance
+            .split_by_ratios(&[
+                Decimal::percent(70),
+                Decimal::percent(20),
+                Decimal::percent(10),
+            ])
+            .unwrap();
+        assert_eq!(
+            shares,
+            vec![
+                NativeBalance(vec![coin(701, "ATOM"), coin(6, "BTC")]),
+                NativeBalance(vec![coin(200, "ATOM"), coin(1, "BTC")]),
+                NativeBalance(vec![coin(100, "ATOM")]),
+            ]
+        );
+        let total = shares
+            .into_iter()
+            .fold(NativeBalance::default(), |sum, share| sum + share);
+        assert_eq!(total, balance);
+
+        let err = balance
+            .split_by_ratios(&[Decimal::percent(70), Decimal::percent(20)])
+            .unwrap_err();
+        assert_eq!(err, BalanceError::InvalidRatios {});
+    }
+
+    mod reference {
+        use super::*;
+
+        type Reference = BTreeMap<String, Uint256>;
+
+        const RUNS: usize = 3000;
+
+        /// A seeded splitmix64 generator, so failures can be reproduced
+        struct Rng(u64);
+
+        impl Rng {
+            fn next(&mut self) -> u64 {
+                self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                let mut z = self.0;
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^ (z >> 31)
+            }
+
+            fn below(&mut self, n: u64) -> u64 {
+                self.next() % n
+            }
+
+            /// Zero, small, u64 and u128 sized, arbitrary and close to `Uint256::MAX`
+            fn amount(&mut self) -> Uint256 {
+                match self.below(6) {
+                    0 => Uint256::zero(),
+                    1 => Uint256::from(self.below(1000)),
+                    2 => Uint256::from(self.next()),
+                    3 => Uint256::from(u128::from(self.next()) << 64 | u128::from(self.next())),
+                    4 => {
+                        let mut bytes = [0u8; 32];
+                        for chunk in bytes.chunks_mut(8) {
+                            chunk.copy_from_slice(&self.next().to_be_bytes());
+                        }
+                        Uint256::from_be_bytes(bytes)
+                    }
+                    _ => Uint256::MAX - Uint256::from(self.below(1000)),
+                }
+            }
+
+            /// Up to 16 coins over up to 12 denoms, so with duplicates and zero amounts
+            fn coins(&mut self) -> Vec<Coin> {
+                let denoms = self.below(12) + 1;
+                (0..self.below(17))
+                    .map(|_| {
+                        let denom = format!("denom{}", self.below(denoms));
+                        Coin::new(self.amount(), denom)
+                    })
+                    .collect()
+            }
+        }
+
+        fn denoms() -> impl Iterator<Item = String> {
+            (0..12).map(|i| format!("denom{i}"))
+        }
+
+        /// Saturating totals per denom, and whether summing them overflows
+        fn reference(coins: &[Coin]) -> (Reference, bool) {
+            let mut totals = Reference::new();
+            let mut overflow = false;
+            for c in coins {
+                let total = totals.entry(c.denom.clone()).or_default();
+                overflow |= total.checked_add(c.amount).is_err();
+                *total = total.saturating_add(c.amount);
+            }
+            totals.retain(|_, amount| !amount.is_zero());
+            (totals, overflow)
+        }
+
+        fn to_reference(balance: &NativeBalance) -> Reference {
+            reference(&balance.0).0
+        }
+
+        fn combine(
+            a: &Reference,
+            b: &Reference,
+            f: impl Fn(Uint256, Uint256) -> Uint256,
+        ) -> Reference {
+            let mut res = Reference::new();
+            for denom in a.keys().chain(b.keys()) {
+                let amount = f(
+                    a.get(denom).copied().unwrap_or_default(),
+                    b.get(denom).copied().unwrap_or_default(),
+                );
+                if !amount.is_zero() {
+                    res.insert(denom.clone(), amount);
+                }
+            }
+            res
+        }
+
+        #[test]
+        fn split_adds_up() {
+            let mut rng = Rng(0x5eed_0001);
+            for _ in 0..RUNS {
+                let balance = NativeBalance(rng.coins());
+                let weights: Vec<_> = (0..rng.below(6))
+                    .map(|i| {
+                        let weight = match rng.below(3) {
+                            0 => Uint256::zero(),
+                            _ => rng.amount(),
+                        };
+                        (i, weight)
+                    })
+                    .collect();
+                let total_weight = weights
+                    .iter()
+                    .try_fold(Uint256::zero(), |sum, (_, w)| sum.checked_add(*w));
+                match balance.split_by_weights(&weights) {
+                    Ok(shares) => {
+                        assert_eq!(shares.len(), weights.len());
+                        let total = shares
+                            .into_iter()
+                            .try_fold(NativeBalance::default(), |sum, (_, share)| {
+                                sum.checked_add_balance(&share)
+                            })
+                            .unwrap();
+                        assert_eq!(to_reference(&total), to_reference(&balance));
+                    }
+                    Err(BalanceError::WeightOverflow {}) => assert!(total_weight.is_err()),
+                    Err(err) => {
+                        assert_eq!(err, BalanceError::ZeroTotalWeight {});
+                        assert_eq!(total_weight, Ok(Uint256::zero()));
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn matches_reference() {
+            let mut rng = Rng(0x5eed_0002);
+            for _ in 0..RUNS {
+                let (a, b) = (rng.coins(), rng.coins());
+                let ((ra, overflow), (rb, _)) = (reference(&a), reference(&b));
+                let (a, b) = (NativeBalance(a), NativeBalance(b));
+
+                // only a balance without overflowing duplicates can be normalized
+                let normalized = NativeBalance::new(a.clone().into_vec());
+                assert_eq!(normalized.is_err(), overflow);
+                let normalized = normalized.unwrap_or_else(|_| a.clone());
+
+                for balance in [&a, &normalized] {
+                    assert_eq!(balance.is_empty(), ra.is_empty());
+                    assert_eq!(
+                        balance
+                            .iter()
+                            .map(|(d, _)| d.to_string())
+                            .collect::<Vec<_>>(),
+                        ra.keys().cloned().collect::<Vec<_>>()
+                    );
+                    for denom in denoms() {
+                        let expected = ra.get(&denom).copied().unwrap_or_default();
+                        assert_eq!(balance.amount_of(&denom), expected);
+                    }
+
+                    let covers = rb
+                        .iter()
+                        .all(|(d, amount)| ra.get(d).is_some_and(|have| have >= amount));
+                    assert_eq!(balance.covers(&b), covers);
+
+                    let diff = combine(&ra, &rb, Uint256::saturating_sub);
+                    assert_eq!(to_reference(&balance.difference(&b)), diff);
+                    let min = combine(&ra, &rb, Uint256::min);
+                    assert_eq!(to_reference(&balance.min(&b)), min.clone());
+                    assert_eq!(to_reference(&balance.intersection(&b)), min);
+                    let max = combine(&ra, &rb, Uint256::max);
+                    assert_eq!(to_reference(&balance.max(&b)), max);
+
+                    // results are normalized
+                    for result in [balance.difference(&b), balance.min(&b), balance.max(&b)] {
+                        assert_eq!(NativeBalance::new(result.clone().into_vec()), Ok(result));
+                    }
+                }
+            }
+        }
+    }
+
     #[test]
     fn normalize_balance() {
         // remove 0 value items and sort
@@ -306,4 +1064,97 @@ mod test {
             NativeBalance(vec![coin(800, "BTC"), coin(444, "ETH")])
         );
     }
+
+    #[test]
+    fn construct_normalized() {
+        let balance = NativeBalance::new(vec![
+            coin(123, "ETH"),
+            coin(0, "BTC"),
+            coin(321, "ETH"),
+            coin(8990, "ATOM"),
+        ])
+        .unwrap();
+        assert_eq!(balance.coins(), &[coin(8990, "ATOM"), coin(444, "ETH")]);
+
+        let err =
+            NativeBalance::new(vec![Coin::new(Uint256::MAX, "ETH"), coin(1, "ETH")]).unwrap_err();
+        assert_eq!(
+            err,
+            BalanceError::Overflow {
+                denom: "ETH".to_string()
+            }
+        );
+
+        // adding zero keeps it normalized
+        let mut balance = NativeBalance::default();
+        balance += coin(0, "BTC");
+        assert_eq!(balance.coins(), &[]);
+    }
+
+    #[test]
+    fn validate_denoms() {
+        let balance = NativeBalance::try_from(vec![
+            coin(5, "ujuno"),
+            coin(
+                7,
+                "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2",
+            ),
+            coin(100, "uatom"),
+        ])
+        .unwrap();
+        assert_eq!(balance.coins()[1], coin(100, "uatom"));
+        assert_eq!(balance.coins().len(), 3);
+
+        for denom in ["ab", "1atom", "u atom", "uatom!", &"a".repeat(129)] {
+            let err = NativeBalance::try_from(vec![coin(1, denom)]).unwrap_err();
+            assert_eq!(
+                err,
+                BalanceError::InvalidDenom {
+                    denom: denom.to_string()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn deserialize_validates() {
+        let balance: NativeBalance = cosmwasm_std::from_json(
+            r#"[{"denom":"ujuno","amount":"5"},{"denom":"uatom","amount":"0"},{"denom":"ujuno","amount":"3"}]"#,
+        )
+        .unwrap();
+        assert_eq!(balance.coins(), &[coin(8, "ujuno")]);
+        let json = cosmwasm_std::to_json_string(&balance).unwrap();
+        assert_eq!(json, r#"[{"denom":"ujuno","amount":"8"}]"#);
+
+        let err = cosmwasm_std::from_json::<NativeBalance>(r#"[{"denom":"1a","amount":"5"}]"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid denom '1a'"), "{err}");
+    }
+
+    #[test]
+    fn parse_coin_string() {
+        let balance: NativeBalance = "100uatom,5ujuno, 7uatom".parse().unwrap();
+        assert_eq!(balance.coins(), &[coin(107, "uatom"), coin(5, "ujuno")]);
+        assert_eq!(balance.to_string(), "uatom107ujuno5");
+
+        let empty: NativeBalance = "".parse().unwrap();
+        assert!(empty.is_empty());
+
+        for input in ["uatom", "100", "-5uatom", "100uatom;5ujuno"] {
+            let err = input.parse::<NativeBalance>().unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    BalanceError::InvalidCoin { .. } | BalanceError::InvalidDenom { .. }
+                ),
+                "{input}: {err}"
+            );
+        }
+        assert_eq!(
+            "5ab".parse::<NativeBalance>().unwrap_err(),
+            BalanceError::InvalidDenom {
+                denom: "ab".to_string()
+            }
+        );
+    }
 }
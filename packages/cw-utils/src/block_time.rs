@@ -0,0 +1,240 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{BlockInfo, Timestamp};
+use thiserror::Error;
+
+use crate::Expiration;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// BlockTimeEstimator converts between heights and times, assuming blocks are produced
+/// at a constant rate from a reference block on. Results are estimates only and must
+/// not be used where exact timing matters.
+#[cw_serde]
+#[derive(Copy)]
+pub struct BlockTimeEstimator {
+    /// Height of the reference block
+    pub height: u64,
+    /// Time of the reference block
+    pub time: Timestamp,
+    /// Average block time in nanoseconds
+    pub nanos_per_block: u64,
+}
+
+impl BlockTimeEstimator {
+    pub fn new(reference: &BlockInfo, nanos_per_block: u64) -> Result<Self, BlockTimeError> {
+        if nanos_per_block == 0 {
+            return Err(BlockTimeError::ZeroBlockTime {});
+        }
+        Ok(BlockTimeEstimator {
+            height: reference.height,
+            time: reference.time,
+            nanos_per_block,
+        })
+    }
+
+    /// Derives the average block time from two observed blocks, using the later
+    /// one as reference
+    pub fn from_blocks(first: &BlockInfo, second: &BlockInfo) -> Result<Self, BlockTimeError> {
+        let (earlier, later) = if first.height <= second.height {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        let blocks = later.height - earlier.height;
+        if blocks == 0 || later.time <= earlier.time {
+            return Err(BlockTimeError::InvalidObservations {});
+        }
+        let nanos = later.time.nanos() - earlier.time.nanos();
+        Self::new(later, nanos / blocks)
+    }
+
+    /// Estimates the time at which the given height is reached
+    pub fn time_at_height(&self, height: u64) -> Timestamp {
+        let now = self.time.nanos();
+        let nanos = if height >= self.height {
+            let delta = (height - self.height).saturating_mul(self.nanos_per_block);
+            now.saturating_add(delta)
+        } else {
+            let delta = (self.height - height).saturating_mul(self.nanos_per_block);
+            now.saturating_sub(delta)
+        };
+        Timestamp::from_nanos(nanos)
+    }
+
+    /// Estimates the first height at or after the given time
+    pub fn height_at_time(&self, time: Timestamp) -> u64 {
+        let (now, then) = (self.time.nanos(), time.nanos());
+        if then >= now {
+            let blocks = (then - now).div_ceil(self.nanos_per_block);
+            self.height.saturating_add(blocks)
+        } else {
+            let blocks = (now - then) / self.nanos_per_block;
+            self.height.saturating_sub(blocks)
+        }
+    }
+
+    /// Estimates when the expiration is hit, None if it never expires.
+    /// Accepts `Scheduled` as well.
+    pub fn estimate_time(&self, expiration: impl Into<Expiration>) -> Option<Timestamp> {
+        match expiration.into() {
+            Expiration::AtHeight(height) => Some(self.time_at_height(height)),
+            Expiration::AtTime(time) => Some(time),
+            Expiration::Never {} => None,
+            Expiration::AtHeightOrTime { height, time } => {
+                Some(self.time_at_height(height).min(time))
+            }
+            Expiration::AtHeightAndTime { height, time } => {
+                Some(self.time_at_height(height).max(time))
+            }
+        }
+    }
+
+    /// Estimates the height at which the expiration is hit, None if it never expires.
+    /// Accepts `Scheduled` as well.
+    pub fn estimate_height(&self, expiration: impl Into<Expiration>) -> Option<u64> {
+        match expiration.into() {
+            Expiration::AtHeight(height) => Some(height),
+            Expiration::AtTime(time) => Some(self.height_at_time(time)),
+            Expiration::Never {} => None,
+            Expiration::AtHeightOrTime { height, time } => {
+                Some(height.min(self.height_at_time(time)))
+            }
+            Expiration::AtHeightAndTime { height, time } => {
+                Some(height.max(self.height_at_time(time)))
+            }
+        }
+    }
+
+    /// Number of seconds the given number of blocks take, rounded down
+    pub(crate) fn blocks_to_seconds(&self, blocks: u64) -> u64 {
+        let nanos = u128::from(blocks) * u128::from(self.nanos_per_block);
+        u64::try_from(nanos / u128::from(NANOS_PER_SECOND)).unwrap_or(u64::MAX)
+    }
+
+    /// Number of blocks needed to cover the given number of seconds, rounded up
+    pub(crate) fn seconds_to_blocks(&self, seconds: u64) -> u64 {
+        let nanos = u128::from(seconds) * u128::from(NANOS_PER_SECOND);
+        u64::try_from(nanos.div_ceil(u128::from(self.nanos_per_block))).unwrap_or(u64::MAX)
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum BlockTimeError {
+    #[error("Block time cannot be zero")]
+    ZeroBlockTime {},
+
+    #[error("Blocks must differ in height and increase in time")]
+    InvalidObservations {},
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Duration, Scheduled};
+
+    fn block(height: u64, seconds: u64) -> BlockInfo {
+        BlockInfo {
+            height,
+            time: Timestamp::from_seconds(seconds),
+            chain_id: "foo".to_string(),
+        }
+    }
+
+    #[test]
+    fn derive_block_time() {
+        let estimator = BlockTimeEstimator::from_blocks(&block(1000, 600), &block(900, 0)).unwrap();
+        assert_eq!(
+            estimator,
+            BlockTimeEstimator {
+                height: 1000,
+                time: Timestamp::from_seconds(600),
+                nanos_per_block: 6 * NANOS_PER_SECOND,
+            }
+        );
+
+        let err =
+            BlockTimeEstimator::from_blocks(&block(1000, 600), &block(1000, 500)).unwrap_err();
+        assert_eq!(err, BlockTimeError::InvalidObservations {});
+        let err = BlockTimeEstimator::from_blocks(&block(900, 600), &block(1000, 600)).unwrap_err();
+        assert_eq!(err, BlockTimeError::InvalidObservations {});
+        let err = BlockTimeEstimator::new(&block(1000, 600), 0).unwrap_err();
+        assert_eq!(err, BlockTimeError::ZeroBlockTime {});
+    }
+
+    #[test]
+    fn estimate_expirations() {
+        let estimator =
+            BlockTimeEstimator::new(&block(1000, 10_000), 5 * NANOS_PER_SECOND).unwrap();
+
+        let at_time = |seconds| Expiration::AtTime(Timestamp::from_seconds(seconds));
+        assert_eq!(
+            estimator.estimate_time(Expiration::AtHeight(1100)),
+            Some(Timestamp::from_seconds(10_500))
+        );
+        assert_eq!(
+            estimator.estimate_time(Expiration::AtHeight(900)),
+            Some(Timestamp::from_seconds(9_500))
+        );
+        assert_eq!(estimator.estimate_height(at_time(10_500)), Some(1100));
+        // the first block at or after the time
+        assert_eq!(estimator.estimate_height(at_time(10_501)), Some(1101));
+        assert_eq!(estimator.estimate_height(at_time(9_502)), Some(901));
+        assert_eq!(estimator.estimate_time(Expiration::Never {}), None);
+        assert_eq!(estimator.estimate_height(Expiration::Never {}), None);
+
+        let or = Expiration::AtHeightOrTime {
+            height: 1100,
+            time: Timestamp::from_seconds(10_200),
+        };
+        assert_eq!(
+            estimator.estimate_time(or),
+            Some(Timestamp::from_seconds(10_200))
+        );
+        assert_eq!(estimator.estimate_height(or), Some(1040));
+        let and = Expiration::AtHeightAndTime {
+            height: 1100,
+            time: Timestamp::from_seconds(10_200),
+        };
+        assert_eq!(
+            estimator.estimate_time(and),
+            Some(Timestamp::from_seconds(10_500))
+        );
+        assert_eq!(estimator.estimate_height(and), Some(1100));
+
+        // scheduled works as well
+        assert_eq!(
+            estimator.estimate_time(Scheduled::AtHeight(1010)),
+            Some(Timestamp::from_seconds(10_050))
+        );
+    }
+
+    #[test]
+    fn convert_durations() {
+        let estimator = BlockTimeEstimator::new(&block(1000, 10_000), 5_500_000_000).unwrap();
+
+        assert_eq!(
+            Duration::Height(100).to_time(&estimator),
+            Duration::Time(550)
+        );
+        assert_eq!(
+            Duration::Time(550).to_height(&estimator),
+            Duration::Height(100)
+        );
+        // rounds up to cover the full time
+        assert_eq!(
+            Duration::Time(551).to_height(&estimator),
+            Duration::Height(101)
+        );
+        // same unit is unchanged
+        assert_eq!(Duration::Time(551).to_time(&estimator), Duration::Time(551));
+        assert_eq!(
+            Duration::Height(3).to_height(&estimator),
+            Duration::Height(3)
+        );
+        // saturates instead of overflowing
+        assert_eq!(
+            Duration::Height(u64::MAX).to_time(&estimator),
+            Duration::Time(u64::MAX)
+        );
+    }
+}
@@ -2,7 +2,14 @@ use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{BlockInfo, StdError, StdResult, Timestamp};
 use std::cmp::Ordering;
 use std::fmt;
-use std::ops::{Add, Mul};
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::humanize::{format_duration, parse_duration, parse_expiration, Rfc3339};
+use crate::{BlockTimeEstimator, Scheduled, TimeParseError};
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
 
 /// Expiration represents a point in time when some event happens.
 /// It can compare with a BlockInfo and will return is_expired() == true
@@ -16,18 +23,53 @@ pub enum Expiration {
     AtTime(Timestamp),
     /// Never will never expire. Used to express the empty variant
     Never {},
+    /// AtHeightOrTime will expire when either `env.block.height` >= height
+    /// or `env.block.time` >= time, whichever comes first
+    AtHeightOrTime { height: u64, time: Timestamp },
+    /// AtHeightAndTime will expire once both `env.block.height` >= height
+    /// and `env.block.time` >= time
+    AtHeightAndTime { height: u64, time: Timestamp },
 }
 
+/// The alternate format `{:#}` is human-friendly, like `at height 1000` or
+/// `at 2024-05-01T12:00:00Z`, and can be parsed back with `FromStr`
 impl fmt::Display for Expiration {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            return match self {
+                Expiration::AtHeight(height) => write!(f, "at height {height}"),
+                Expiration::AtTime(time) => write!(f, "at {}", Rfc3339(time)),
+                Expiration::Never {} => write!(f, "never"),
+                Expiration::AtHeightOrTime { height, time } => {
+                    write!(f, "at height {height} or {}", Rfc3339(time))
+                }
+                Expiration::AtHeightAndTime { height, time } => {
+                    write!(f, "at height {height} and {}", Rfc3339(time))
+                }
+            };
+        }
         match self {
             Expiration::AtHeight(height) => write!(f, "expiration height: {height}"),
             Expiration::AtTime(time) => write!(f, "expiration time: {time}"),
             Expiration::Never {} => write!(f, "expiration: never"),
+            Expiration::AtHeightOrTime { height, time } => {
+                write!(f, "expiration height: {height} or time: {time}")
+            }
+            Expiration::AtHeightAndTime { height, time } => {
+                write!(f, "expiration height: {height} and time: {time}")
+            }
         }
     }
 }
 
+impl FromStr for Expiration {
+    type Err = TimeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_expiration(s)
+    }
+}
+
 /// The default (empty value) is to never expire
 impl Default for Expiration {
     fn default() -> Self {
@@ -41,6 +83,112 @@ impl Expiration {
             Expiration::AtHeight(height) => block.height >= *height,
             Expiration::AtTime(time) => block.time >= *time,
             Expiration::Never {} => false,
+            Expiration::AtHeightOrTime { height, time } => {
+                block.height >= *height || block.time >= *time
+            }
+            Expiration::AtHeightAndTime { height, time } => {
+                block.height >= *height && block.time >= *time
+            }
+        }
+    }
+
+    /// Compares two expirations of any kind, by estimating when heights are reached.
+    /// Never is after everything else. Unlike `partial_cmp`, this is a total order,
+    /// so it can be used to sort mixed expirations.
+    pub fn estimated_cmp(&self, other: &Expiration, estimator: &BlockTimeEstimator) -> Ordering {
+        match (
+            estimator.estimate_time(*self),
+            estimator.estimate_time(*other),
+        ) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    /// Moves the expiration later by `duration`. Never stays never.
+    pub fn checked_add(self, duration: Duration) -> Result<Expiration, ExpirationError> {
+        match (self, duration) {
+            (Expiration::AtTime(t), Duration::Time(delta)) => delta
+                .checked_mul(NANOS_PER_SECOND)
+                .and_then(|delta| t.nanos().checked_add(delta))
+                .map(|nanos| Expiration::AtTime(Timestamp::from_nanos(nanos)))
+                .ok_or(ExpirationError::Overflow {}),
+            (Expiration::AtHeight(h), Duration::Height(delta)) => h
+                .checked_add(delta)
+                .map(Expiration::AtHeight)
+                .ok_or(ExpirationError::Overflow {}),
+            (Expiration::Never {}, _) => Ok(Expiration::Never {}),
+            (Expiration::AtHeightOrTime { .. } | Expiration::AtHeightAndTime { .. }, _) => {
+                Err(ExpirationError::Combined {})
+            }
+            _ => Err(ExpirationError::MismatchedUnits {}),
+        }
+    }
+
+    /// Moves the expiration earlier by `duration`. Never stays never.
+    pub fn checked_sub(self, duration: Duration) -> Result<Expiration, ExpirationError> {
+        match (self, duration) {
+            (Expiration::AtTime(t), Duration::Time(delta)) => delta
+                .checked_mul(NANOS_PER_SECOND)
+                .and_then(|delta| t.nanos().checked_sub(delta))
+                .map(|nanos| Expiration::AtTime(Timestamp::from_nanos(nanos)))
+                .ok_or(ExpirationError::Underflow {}),
+            (Expiration::AtHeight(h), Duration::Height(delta)) => h
+                .checked_sub(delta)
+                .map(Expiration::AtHeight)
+                .ok_or(ExpirationError::Underflow {}),
+            (Expiration::Never {}, _) => Ok(Expiration::Never {}),
+            (Expiration::AtHeightOrTime { .. } | Expiration::AtHeightAndTime { .. }, _) => {
+                Err(ExpirationError::Combined {})
+            }
+            _ => Err(ExpirationError::MismatchedUnits {}),
+        }
+    }
+
+    /// Returns how long it takes from `block` until this expires, zero if it already has.
+    /// Time is rounded up to full seconds, so that `remaining.after(block)` is expired.
+    /// Returns None for Never, and for combined expirations as they have no single unit.
+    pub fn remaining(&self, block: &BlockInfo) -> Option<Duration> {
+        match self {
+            Expiration::AtHeight(height) => {
+                Some(Duration::Height(height.saturating_sub(block.height)))
+            }
+            Expiration::AtTime(time) => {
+                let nanos = time.nanos().saturating_sub(block.time.nanos());
+                Some(Duration::Time(nanos.div_ceil(NANOS_PER_SECOND)))
+            }
+            Expiration::Never {}
+            | Expiration::AtHeightOrTime { .. }
+            | Expiration::AtHeightAndTime { .. } => None,
+        }
+    }
+
+    /// Returns the earlier of both, which must be of the same kind or Never
+    pub fn min(self, other: Expiration) -> Result<Expiration, ExpirationError> {
+        match self.partial_cmp(&other) {
+            Some(Ordering::Greater) => Ok(other),
+            Some(_) => Ok(self),
+            None => Err(ExpirationError::MismatchedUnits {}),
+        }
+    }
+
+    /// Returns the later of both, which must be of the same kind or Never
+    pub fn max(self, other: Expiration) -> Result<Expiration, ExpirationError> {
+        match self.partial_cmp(&other) {
+            Some(Ordering::Less) => Ok(other),
+            Some(_) => Ok(self),
+            None => Err(ExpirationError::MismatchedUnits {}),
+        }
+    }
+}
+
+impl From<Scheduled> for Expiration {
+    fn from(scheduled: Scheduled) -> Self {
+        match scheduled {
+            Scheduled::AtHeight(height) => Expiration::AtHeight(height),
+            Scheduled::AtTime(time) => Expiration::AtTime(time),
         }
     }
 }
@@ -49,19 +197,66 @@ impl Add<Duration> for Expiration {
     type Output = StdResult<Expiration>;
 
     fn add(self, duration: Duration) -> StdResult<Expiration> {
-        match (self, duration) {
-            (Expiration::AtTime(t), Duration::Time(delta)) => {
-                Ok(Expiration::AtTime(t.plus_seconds(delta)))
+        self.checked_add(duration).map_err(add_error)
+    }
+}
+
+/// Converts errors of the checked additions for the `Add` implementations,
+/// which keep the message they always had for mismatched units
+pub(crate) fn add_error(err: ExpirationError) -> StdError {
+    match err {
+        ExpirationError::MismatchedUnits {} => StdError::msg("Cannot add height and time"),
+        err => StdError::msg(err),
+    }
+}
+
+/// The duration from `rhs` until `self`. Sub-second differences are rounded down.
+impl Sub<Expiration> for Expiration {
+    type Output = Result<Duration, ExpirationError>;
+
+    fn sub(self, rhs: Expiration) -> Result<Duration, ExpirationError> {
+        match (self, rhs) {
+            (Expiration::AtHeight(h1), Expiration::AtHeight(h2)) => h1
+                .checked_sub(h2)
+                .map(Duration::Height)
+                .ok_or(ExpirationError::Underflow {}),
+            (Expiration::AtTime(t1), Expiration::AtTime(t2)) => t1
+                .nanos()
+                .checked_sub(t2.nanos())
+                .map(|nanos| Duration::Time(nanos / NANOS_PER_SECOND))
+                .ok_or(ExpirationError::Underflow {}),
+            (Expiration::Never {}, _) | (_, Expiration::Never {}) => {
+                Err(ExpirationError::NeverExpires {})
             }
-            (Expiration::AtHeight(h), Duration::Height(delta)) => {
-                Ok(Expiration::AtHeight(h + delta))
+            (Expiration::AtHeightOrTime { .. } | Expiration::AtHeightAndTime { .. }, _)
+            | (_, Expiration::AtHeightOrTime { .. } | Expiration::AtHeightAndTime { .. }) => {
+                Err(ExpirationError::Combined {})
             }
-            (Expiration::Never {}, _) => Ok(Expiration::Never {}),
-            _ => Err(StdError::msg("Cannot add height and time")),
+            _ => Err(ExpirationError::MismatchedUnits {}),
         }
     }
 }
 
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ExpirationError {
+    #[error("Cannot combine height and time")]
+    MismatchedUnits {},
+
+    #[error("Expiration overflow")]
+    Overflow {},
+
+    #[error("Expiration underflow")]
+    Underflow {},
+
+    #[error("Cannot measure a duration to an expiration that never happens")]
+    NeverExpires {},
+
+    #[error(
+        "Cannot shift or measure an expiration combining height and time by a single duration"
+    )]
+    Combined {},
+}
+
 // TODO: does this make sense? do we get expected info/error when None is returned???
 impl PartialOrd for Expiration {
     fn partial_cmp(&self, other: &Expiration) -> Option<Ordering> {
@@ -69,6 +264,32 @@ impl PartialOrd for Expiration {
             // compare if both height or both time
             (Expiration::AtHeight(h1), Expiration::AtHeight(h2)) => Some(h1.cmp(h2)),
             (Expiration::AtTime(t1), Expiration::AtTime(t2)) => Some(t1.cmp(t2)),
+            // combined ones compare if height and time agree
+            (
+                Expiration::AtHeightOrTime {
+                    height: h1,
+                    time: t1,
+                },
+                Expiration::AtHeightOrTime {
+                    height: h2,
+                    time: t2,
+                },
+            )
+            | (
+                Expiration::AtHeightAndTime {
+                    height: h1,
+                    time: t1,
+                },
+                Expiration::AtHeightAndTime {
+                    height: h2,
+                    time: t2,
+                },
+            ) => match (h1.cmp(h2), t1.cmp(t2)) {
+                (a, b) if a == b => Some(a),
+                (Ordering::Equal, b) => Some(b),
+                (a, Ordering::Equal) => Some(a),
+                _ => None,
+            },
             // if at least one is never, we can compare with anything
             (Expiration::Never {}, Expiration::Never {}) => Some(Ordering::Equal),
             (Expiration::Never {}, _) => Some(Ordering::Greater),
@@ -94,8 +315,13 @@ pub enum Duration {
     Time(u64),
 }
 
+/// The alternate format `{:#}` is human-friendly, like `in 12 hours 30 minutes`
+/// or `in 1000 blocks`, and can be parsed back with `FromStr`
 impl fmt::Display for Duration {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            return format_duration(f, self);
+        }
         match self {
             Duration::Height(height) => write!(f, "height: {height}"),
             Duration::Time(time) => write!(f, "time: {time}"),
@@ -103,8 +329,18 @@ impl fmt::Display for Duration {
     }
 }
 
+/// Parses `"7d"`, `"12h30m"` or `"1000 blocks"`
+impl FromStr for Duration {
+    type Err = TimeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_duration(s)
+    }
+}
+
 impl Duration {
-    /// Create an expiration for Duration after current block
+    /// Create an expiration for Duration after current block.
+    /// Panics on overflow, use `checked_after` for durations that are not trusted.
     pub fn after(&self, block: &BlockInfo) -> Expiration {
         match self {
             Duration::Height(h) => Expiration::AtHeight(block.height + h),
@@ -112,6 +348,54 @@ impl Duration {
         }
     }
 
+    /// Create an expiration for Duration after current block, or an error on overflow
+    pub fn checked_after(&self, block: &BlockInfo) -> Result<Expiration, ExpirationError> {
+        let now = match self {
+            Duration::Height(_) => Expiration::AtHeight(block.height),
+            Duration::Time(_) => Expiration::AtTime(block.time),
+        };
+        now.checked_add(*self)
+    }
+
+    pub fn checked_add(self, rhs: Duration) -> Result<Duration, ExpirationError> {
+        match (self, rhs) {
+            (Duration::Time(t), Duration::Time(t2)) => t
+                .checked_add(t2)
+                .map(Duration::Time)
+                .ok_or(ExpirationError::Overflow {}),
+            (Duration::Height(h), Duration::Height(h2)) => h
+                .checked_add(h2)
+                .map(Duration::Height)
+                .ok_or(ExpirationError::Overflow {}),
+            _ => Err(ExpirationError::MismatchedUnits {}),
+        }
+    }
+
+    pub fn checked_mul(self, rhs: u64) -> Result<Duration, ExpirationError> {
+        match self {
+            Duration::Time(t) => t.checked_mul(rhs).map(Duration::Time),
+            Duration::Height(h) => h.checked_mul(rhs).map(Duration::Height),
+        }
+        .ok_or(ExpirationError::Overflow {})
+    }
+
+    /// Converts to a time based duration, estimating the time of height based ones
+    pub fn to_time(&self, estimator: &BlockTimeEstimator) -> Duration {
+        match self {
+            Duration::Height(h) => Duration::Time(estimator.blocks_to_seconds(*h)),
+            Duration::Time(t) => Duration::Time(*t),
+        }
+    }
+
+    /// Converts to a height based duration, estimating the blocks needed to cover
+    /// time based ones
+    pub fn to_height(&self, estimator: &BlockTimeEstimator) -> Duration {
+        match self {
+            Duration::Height(h) => Duration::Height(*h),
+            Duration::Time(t) => Duration::Height(estimator.seconds_to_blocks(*t)),
+        }
+    }
+
     // creates a number just a little bigger, so we can use it to pass expiration point
     pub fn plus_one(&self) -> Duration {
         match self {
@@ -119,20 +403,25 @@ impl Duration {
             Duration::Time(t) => Duration::Time(t + 1),
         }
     }
+
+    /// Like `plus_one`, but returns an error instead of overflowing
+    pub fn checked_plus_one(&self) -> Result<Duration, ExpirationError> {
+        match self {
+            Duration::Height(_) => self.checked_add(Duration::Height(1)),
+            Duration::Time(_) => self.checked_add(Duration::Time(1)),
+        }
+    }
 }
 
 impl Add<Duration> for Duration {
     type Output = StdResult<Duration>;
 
     fn add(self, rhs: Duration) -> StdResult<Duration> {
-        match (self, rhs) {
-            (Duration::Time(t), Duration::Time(t2)) => Ok(Duration::Time(t + t2)),
-            (Duration::Height(h), Duration::Height(h2)) => Ok(Duration::Height(h + h2)),
-            _ => Err(StdError::msg("Cannot add height and time")),
-        }
+        self.checked_add(rhs).map_err(add_error)
     }
 }
 
+/// Panics on overflow, use `checked_mul` for factors that are not trusted
 impl Mul<u64> for Duration {
     type Output = Duration;
 
@@ -147,6 +436,7 @@ impl Mul<u64> for Duration {
 #[cfg(test)]
 mod test {
     use super::*;
+    use cosmwasm_std::{from_json, to_json_string};
 
     #[test]
     fn compare_expiration() {
@@ -204,6 +494,14 @@ mod test {
 
         // mismatched
         let end = Expiration::AtHeight(12345) + Duration::Time(1500);
+        let err = end.unwrap_err();
+        assert!(
+            err.to_string().contains("Cannot add height and time"),
+            "{err}"
+        );
+
+        // overflow is an error rather than a panic
+        let end = Expiration::AtHeight(u64::MAX) + Duration::Height(1);
         end.unwrap_err();
 
         // // not possible other way
@@ -211,6 +509,302 @@ mod test {
         // assert_eq!(end.unwrap(), Expiration::AtTime(51000));
     }
 
+    #[test]
+    fn expiration_checked_math() {
+        let at_time = |seconds| Expiration::AtTime(Timestamp::from_seconds(seconds));
+
+        assert_eq!(
+            Expiration::AtHeight(100).checked_add(Duration::Height(50)),
+            Ok(Expiration::AtHeight(150))
+        );
+        assert_eq!(
+            Expiration::AtHeight(u64::MAX).checked_add(Duration::Height(1)),
+            Err(ExpirationError::Overflow {})
+        );
+        assert_eq!(
+            at_time(1000).checked_add(Duration::Time(u64::MAX)),
+            Err(ExpirationError::Overflow {})
+        );
+        // the operator reports overflow as an error instead of panicking
+        (at_time(1000) + Duration::Time(u64::MAX / 2)).unwrap_err();
+
+        assert_eq!(
+            at_time(1000).checked_sub(Duration::Time(400)),
+            Ok(at_time(600))
+        );
+        assert_eq!(
+            Expiration::AtHeight(100).checked_sub(Duration::Height(101)),
+            Err(ExpirationError::Underflow {})
+        );
+        assert_eq!(
+            Expiration::Never {}.checked_sub(HOUR),
+            Ok(Expiration::Never {})
+        );
+        assert_eq!(
+            Expiration::AtHeight(100).checked_sub(HOUR),
+            Err(ExpirationError::MismatchedUnits {})
+        );
+    }
+
+    #[test]
+    fn expiration_difference() {
+        let at_time = |seconds| Expiration::AtTime(Timestamp::from_seconds(seconds));
+
+        assert_eq!(
+            Expiration::AtHeight(150) - Expiration::AtHeight(100),
+            Ok(Duration::Height(50))
+        );
+        assert_eq!(at_time(1500) - at_time(1000), Ok(Duration::Time(500)));
+        // sub-second differences are rounded down
+        let later = Expiration::AtTime(Timestamp::from_nanos(1_999_999_999));
+        assert_eq!(later - at_time(1), Ok(Duration::Time(0)));
+
+        assert_eq!(
+            Expiration::AtHeight(100) - Expiration::AtHeight(150),
+            Err(ExpirationError::Underflow {})
+        );
+        assert_eq!(
+            Expiration::Never {} - Expiration::AtHeight(150),
+            Err(ExpirationError::NeverExpires {})
+        );
+        assert_eq!(
+            at_time(1000) - Expiration::AtHeight(150),
+            Err(ExpirationError::MismatchedUnits {})
+        );
+    }
+
+    #[test]
+    fn expiration_remaining() {
+        let block = BlockInfo {
+            height: 1000,
+            time: Timestamp::from_nanos(7_777_000_000_001),
+            chain_id: "foo".to_string(),
+        };
+
+        assert_eq!(
+            Expiration::AtHeight(1200).remaining(&block),
+            Some(Duration::Height(200))
+        );
+        assert_eq!(
+            Expiration::AtHeight(900).remaining(&block),
+            Some(Duration::Height(0))
+        );
+        // rounds up to full seconds
+        let end = Expiration::AtTime(Timestamp::from_seconds(7800));
+        let remaining = end.remaining(&block).unwrap();
+        assert_eq!(remaining, Duration::Time(23));
+        assert!(end.is_expired(&BlockInfo {
+            time: block.time.plus_seconds(23),
+            ..block.clone()
+        }));
+        assert_eq!(
+            Expiration::AtTime(Timestamp::from_seconds(7000)).remaining(&block),
+            Some(Duration::Time(0))
+        );
+        assert_eq!(Expiration::Never {}.remaining(&block), None);
+    }
+
+    #[test]
+    fn expiration_min_max() {
+        let early = Expiration::AtHeight(100);
+        let late = Expiration::AtHeight(200);
+        assert_eq!(early.min(late), Ok(early));
+        assert_eq!(late.min(early), Ok(early));
+        assert_eq!(early.max(late), Ok(late));
+        assert_eq!(late.max(Expiration::Never {}), Ok(Expiration::Never {}));
+        assert_eq!(late.min(Expiration::Never {}), Ok(late));
+
+        let time = Expiration::AtTime(Timestamp::from_seconds(100));
+        assert_eq!(early.min(time), Err(ExpirationError::MismatchedUnits {}));
+        assert_eq!(time.max(early), Err(ExpirationError::MismatchedUnits {}));
+    }
+
+    #[test]
+    fn combined_expiration() {
+        let block = |height, seconds| BlockInfo {
+            height,
+            time: Timestamp::from_seconds(seconds),
+            chain_id: "foo".to_string(),
+        };
+        let or = Expiration::AtHeightOrTime {
+            height: 100,
+            time: Timestamp::from_seconds(1000),
+        };
+        let and = Expiration::AtHeightAndTime {
+            height: 100,
+            time: Timestamp::from_seconds(1000),
+        };
+
+        assert!(!or.is_expired(&block(99, 999)));
+        assert!(or.is_expired(&block(100, 999)));
+        assert!(or.is_expired(&block(99, 1000)));
+
+        assert!(!and.is_expired(&block(100, 999)));
+        assert!(!and.is_expired(&block(99, 1000)));
+        assert!(and.is_expired(&block(100, 1000)));
+
+        assert_eq!(
+            or.to_string(),
+            "expiration height: 100 or time: 1000.000000000"
+        );
+        assert_eq!(
+            and.to_string(),
+            "expiration height: 100 and time: 1000.000000000"
+        );
+
+        // combined ones only compare to their own kind if height and time agree
+        let later = Expiration::AtHeightOrTime {
+            height: 100,
+            time: Timestamp::from_seconds(2000),
+        };
+        assert!(or < later);
+        let crossed = Expiration::AtHeightOrTime {
+            height: 50,
+            time: Timestamp::from_seconds(2000),
+        };
+        assert_eq!(or.partial_cmp(&crossed), None);
+        assert_eq!(or.partial_cmp(&and), None);
+        assert_eq!(or.partial_cmp(&Expiration::AtHeight(100)), None);
+        assert!(or < Expiration::Never {});
+
+        // a single duration cannot move both height and time
+        assert_eq!(
+            or.checked_add(Duration::Height(5)),
+            Err(ExpirationError::Combined {})
+        );
+        assert_eq!(
+            and.checked_sub(Duration::Time(5)),
+            Err(ExpirationError::Combined {})
+        );
+        assert_eq!(
+            or - Expiration::AtHeight(5),
+            Err(ExpirationError::Combined {})
+        );
+        assert_eq!(
+            Expiration::AtTime(Timestamp::from_seconds(5)) - and,
+            Err(ExpirationError::Combined {})
+        );
+    }
+
+    #[test]
+    fn combined_expiration_serialization() {
+        // existing variants keep their format
+        assert_eq!(
+            to_json_string(&Expiration::AtHeight(100)).unwrap(),
+            r#"{"at_height":100}"#
+        );
+        assert_eq!(
+            to_json_string(&Expiration::Never {}).unwrap(),
+            r#"{"never":{}}"#
+        );
+
+        let or = Expiration::AtHeightOrTime {
+            height: 100,
+            time: Timestamp::from_seconds(1000),
+        };
+        let json = to_json_string(&or).unwrap();
+        assert_eq!(
+            json,
+            r#"{"at_height_or_time":{"height":100,"time":"1000000000000"}}"#
+        );
+        assert_eq!(from_json::<Expiration>(json).unwrap(), or);
+    }
+
+    #[test]
+    fn estimated_ordering() {
+        let block = BlockInfo {
+            height: 1000,
+            time: Timestamp::from_seconds(10_000),
+            chain_id: "foo".to_string(),
+        };
+        let estimator = BlockTimeEstimator::new(&block, 5 * NANOS_PER_SECOND).unwrap();
+
+        let mut expirations = vec![
+            Expiration::Never {},
+            // ~10_500
+            Expiration::AtHeight(1100),
+            Expiration::AtTime(Timestamp::from_seconds(10_400)),
+            // min(~10_500, 12_000)
+            Expiration::AtHeightOrTime {
+                height: 1100,
+                time: Timestamp::from_seconds(12_000),
+            },
+            // max(~9_500, 10_200)
+            Expiration::AtHeightAndTime {
+                height: 900,
+                time: Timestamp::from_seconds(10_200),
+            },
+            // in the past
+            Expiration::AtHeight(500),
+        ];
+        expirations.sort_by(|a, b| a.estimated_cmp(b, &estimator));
+        assert_eq!(
+            expirations,
+            vec![
+                Expiration::AtHeight(500),
+                Expiration::AtHeightAndTime {
+                    height: 900,
+                    time: Timestamp::from_seconds(10_200),
+                },
+                Expiration::AtTime(Timestamp::from_seconds(10_400)),
+                Expiration::AtHeight(1100),
+                Expiration::AtHeightOrTime {
+                    height: 1100,
+                    time: Timestamp::from_seconds(12_000),
+                },
+                Expiration::Never {},
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_and_humanize() {
+        let cases = [
+            ("7d", Duration::Time(7 * 24 * 60 * 60), "in 1 week"),
+            ("12h30m", Duration::Time(45000), "in 12 hours 30 minutes"),
+            ("1000 blocks", Duration::Height(1000), "in 1000 blocks"),
+            ("1 block", Duration::Height(1), "in 1 block"),
+        ];
+        for (input, duration, human) in cases {
+            assert_eq!(input.parse::<Duration>().unwrap(), duration);
+            assert_eq!(format!("{duration:#}"), human);
+            assert_eq!(human.parse::<Duration>().unwrap(), duration);
+        }
+        // the plain format is unchanged
+        assert_eq!(Duration::Time(604800).to_string(), "time: 604800");
+
+        let time = Timestamp::from_seconds(1_714_564_800);
+        let cases = [
+            ("never", Expiration::Never {}, "never"),
+            ("height 1000", Expiration::AtHeight(1000), "at height 1000"),
+            (
+                "2024-05-01T14:00:00+02:00",
+                Expiration::AtTime(time),
+                "at 2024-05-01T12:00:00Z",
+            ),
+            (
+                "2024-05-01T12:00:00Z or block 1000",
+                Expiration::AtHeightOrTime { height: 1000, time },
+                "at height 1000 or 2024-05-01T12:00:00Z",
+            ),
+            (
+                "height 1000 and 2024-05-01T12:00:00Z",
+                Expiration::AtHeightAndTime { height: 1000, time },
+                "at height 1000 and 2024-05-01T12:00:00Z",
+            ),
+        ];
+        for (input, expiration, human) in cases {
+            assert_eq!(input.parse::<Expiration>().unwrap(), expiration);
+            assert_eq!(format!("{expiration:#}"), human);
+            assert_eq!(human.parse::<Expiration>().unwrap(), expiration);
+        }
+
+        let err = "height 5 or height 6".parse::<Expiration>().unwrap_err();
+        assert!(matches!(err, TimeParseError::Unrecognized { .. }));
+        let err = "tomorrow".parse::<Expiration>().unwrap_err();
+        assert!(matches!(err, TimeParseError::InvalidTimestamp { .. }));
+    }
+
     #[test]
     fn block_plus_duration() {
         let block = BlockInfo {
@@ -224,6 +818,13 @@ mod test {
 
         let end = Duration::Time(1212).after(&block);
         assert_eq!(Expiration::AtTime(Timestamp::from_seconds(8989)), end);
+
+        let end = Duration::Time(1212).checked_after(&block);
+        assert_eq!(Ok(Expiration::AtTime(Timestamp::from_seconds(8989))), end);
+        let end = Duration::Height(u64::MAX).checked_after(&block);
+        assert_eq!(Err(ExpirationError::Overflow {}), end);
+        let end = Duration::Time(u64::MAX / 1_000_000_000).checked_after(&block);
+        assert_eq!(Err(ExpirationError::Overflow {}), end);
     }
 
     #[test]
@@ -233,5 +834,34 @@ mod test {
 
         let days = DAY * 3;
         assert_eq!(Duration::Time(3 * 24 * 60 * 60), days);
+
+        // checked counterparts
+        assert_eq!(
+            Duration::Height(444).checked_add(Duration::Height(555)),
+            Ok(Duration::Height(999))
+        );
+        assert_eq!(
+            Duration::Height(u64::MAX).checked_add(Duration::Height(1)),
+            Err(ExpirationError::Overflow {})
+        );
+        assert_eq!(
+            Duration::Height(1).checked_add(Duration::Time(1)),
+            Err(ExpirationError::MismatchedUnits {})
+        );
+        let err = (Duration::Height(u64::MAX) + Duration::Height(1)).unwrap_err();
+        assert!(err.to_string().contains("Expiration overflow"), "{err}");
+        let err = (Duration::Height(1) + Duration::Time(1)).unwrap_err();
+        assert!(
+            err.to_string().contains("Cannot add height and time"),
+            "{err}"
+        );
+
+        assert_eq!(DAY.checked_mul(3), Ok(days));
+        assert_eq!(DAY.checked_mul(u64::MAX), Err(ExpirationError::Overflow {}));
+        assert_eq!(Duration::Time(5).checked_plus_one(), Ok(Duration::Time(6)));
+        assert_eq!(
+            Duration::Height(u64::MAX).checked_plus_one(),
+            Err(ExpirationError::Overflow {})
+        );
     }
 }
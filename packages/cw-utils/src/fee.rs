@@ -0,0 +1,231 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{BankMsg, Coin, Decimal, MessageInfo};
+
+use crate::payment::check_duplicates;
+use crate::{NativeBalance, PaymentError};
+
+/// FeeConfig describes the protocol fee taken out of the funds sent with a message
+#[cw_serde]
+pub enum FeeConfig {
+    /// A fixed amount of one denom
+    Fixed(Coin),
+    /// A share of every denom sent, rounded down
+    Percentage(Decimal),
+    /// A share of every denom sent, but at least `min`
+    PercentageWithMinimum { percentage: Decimal, min: Coin },
+}
+
+impl FeeConfig {
+    /// returns an error if the fee can take more than was sent
+    pub fn validate(&self) -> Result<(), PaymentError> {
+        match self {
+            FeeConfig::Percentage(percentage)
+            | FeeConfig::PercentageWithMinimum { percentage, .. }
+                if *percentage > Decimal::one() =>
+            {
+                Err(PaymentError::InvalidFeePercentage {})
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Splits the funds sent into the net amount and the fee.
+/// Returns an error if not enough was sent to cover the fee.
+pub fn apply_fee(
+    info: &MessageInfo,
+    config: &FeeConfig,
+) -> Result<(NativeBalance, NativeBalance), PaymentError> {
+    config.validate()?;
+    check_duplicates(info)?;
+    let sent = NativeBalance::new(info.funds.clone()).expect("denoms are unique");
+
+    let (percentage, min) = match config {
+        FeeConfig::Fixed(fee) => (Decimal::zero(), fee),
+        FeeConfig::Percentage(percentage) => {
+            let fee = percentage_of(&sent, *percentage)?;
+            return Ok((sent.difference(&fee), fee));
+        }
+        FeeConfig::PercentageWithMinimum { percentage, min } => (*percentage, min),
+    };
+    let available = sent.amount_of(&min.denom);
+    if available < min.amount {
+        return Err(PaymentError::InsufficientFunds {
+            denom: min.denom.clone(),
+            required: min.amount,
+            sent: available,
+        });
+    }
+    let min = NativeBalance::new(vec![min.clone()]).expect("single coin cannot overflow");
+    let fee = percentage_of(&sent, percentage)?.max(&min);
+    Ok((sent.difference(&fee), fee))
+}
+
+fn percentage_of(sent: &NativeBalance, percentage: Decimal) -> Result<NativeBalance, PaymentError> {
+    sent.clone()
+        .checked_mul_ratio(percentage)
+        .map_err(|_| PaymentError::InvalidFeePercentage {})
+}
+
+/// Creates the `BankMsg::Send` paying the fee to the collector, None if there is no fee
+pub fn fee_msg(collector: impl Into<String>, fee: NativeBalance) -> Option<BankMsg> {
+    send_msg(collector.into(), fee)
+}
+
+/// Creates the `BankMsg::Send` returning the refund to the sender of the message,
+/// None if there is nothing to refund
+pub fn refund_msg(info: &MessageInfo, refund: NativeBalance) -> Option<BankMsg> {
+    send_msg(info.sender.to_string(), refund)
+}
+
+fn send_msg(to_address: String, amount: NativeBalance) -> Option<BankMsg> {
+    if amount.is_empty() {
+        None
+    } else {
+        Some(BankMsg::Send {
+            to_address,
+            amount: amount.into_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::testing::{message_info, mock_dependencies};
+    use cosmwasm_std::{coin, coins, Uint256};
+
+    const SENDER: &str = "sender";
+
+    fn balance(coins: Vec<Coin>) -> NativeBalance {
+        NativeBalance::new(coins).unwrap()
+    }
+
+    #[test]
+    fn fixed_fee() {
+        let deps = mock_dependencies();
+        let sender = deps.api.addr_make(SENDER);
+        let config = FeeConfig::Fixed(coin(10, "uatom"));
+
+        let info = message_info(&sender, &[coin(100, "uatom"), coin(5, "ujuno")]);
+        let (net, fee) = apply_fee(&info, &config).unwrap();
+        assert_eq!(net, balance(vec![coin(90, "uatom"), coin(5, "ujuno")]));
+        assert_eq!(fee, balance(vec![coin(10, "uatom")]));
+
+        let info = message_info(&sender, &coins(10, "uatom"));
+        let (net, fee) = apply_fee(&info, &config).unwrap();
+        assert!(net.is_empty());
+        assert_eq!(fee, balance(coins(10, "uatom")));
+
+        let info = message_info(&sender, &coins(9, "uatom"));
+        let err = apply_fee(&info, &config).unwrap_err();
+        assert_eq!(
+            err,
+            PaymentError::InsufficientFunds {
+                denom: "uatom".to_string(),
+                required: Uint256::new(10),
+                sent: Uint256::new(9),
+            }
+        );
+
+        let info = message_info(&sender, &[]);
+        let err = apply_fee(&info, &config).unwrap_err();
+        assert_eq!(
+            err,
+            PaymentError::InsufficientFunds {
+                denom: "uatom".to_string(),
+                required: Uint256::new(10),
+                sent: Uint256::zero(),
+            }
+        );
+    }
+
+    #[test]
+    fn percentage_fee() {
+        let deps = mock_dependencies();
+        let sender = deps.api.addr_make(SENDER);
+        let config = FeeConfig::Percentage(Decimal::percent(3));
+
+        let info = message_info(&sender, &[coin(1000, "uatom"), coin(50, "ujuno")]);
+        let (net, fee) = apply_fee(&info, &config).unwrap();
+        // 1.5ujuno is rounded down
+        assert_eq!(fee, balance(vec![coin(30, "uatom"), coin(1, "ujuno")]));
+        assert_eq!(net, balance(vec![coin(970, "uatom"), coin(49, "ujuno")]));
+
+        let (net, fee) = apply_fee(&message_info(&sender, &[]), &config).unwrap();
+        assert!(net.is_empty());
+        assert!(fee.is_empty());
+
+        let config = FeeConfig::Percentage(Decimal::percent(101));
+        let err = apply_fee(&info, &config).unwrap_err();
+        assert_eq!(err, PaymentError::InvalidFeePercentage {});
+    }
+
+    #[test]
+    fn percentage_fee_with_minimum() {
+        let deps = mock_dependencies();
+        let sender = deps.api.addr_make(SENDER);
+        let config = FeeConfig::PercentageWithMinimum {
+            percentage: Decimal::percent(1),
+            min: coin(5, "uatom"),
+        };
+
+        // percentage is above the minimum
+        let info = message_info(&sender, &coins(1000, "uatom"));
+        let (net, fee) = apply_fee(&info, &config).unwrap();
+        assert_eq!(fee, balance(coins(10, "uatom")));
+        assert_eq!(net, balance(coins(990, "uatom")));
+
+        // minimum applies
+        let info = message_info(&sender, &[coin(100, "uatom"), coin(200, "ujuno")]);
+        let (net, fee) = apply_fee(&info, &config).unwrap();
+        assert_eq!(fee, balance(vec![coin(5, "uatom"), coin(2, "ujuno")]));
+        assert_eq!(net, balance(vec![coin(95, "uatom"), coin(198, "ujuno")]));
+
+        let info = message_info(&sender, &coins(200, "ujuno"));
+        let err = apply_fee(&info, &config).unwrap_err();
+        assert_eq!(
+            err,
+            PaymentError::InsufficientFunds {
+                denom: "uatom".to_string(),
+                required: Uint256::new(5),
+                sent: Uint256::zero(),
+            }
+        );
+
+        let info = message_info(&sender, &[coin(100, "uatom"), coin(1, "uatom")]);
+        let err = apply_fee(&info, &config).unwrap_err();
+        assert_eq!(
+            err,
+            PaymentError::DuplicateDenom {
+                denom: "uatom".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn fee_and_refund_messages() {
+        let deps = mock_dependencies();
+        let sender = deps.api.addr_make(SENDER);
+        let info = message_info(&sender, &[coin(100, "uatom"), coin(5, "ujuno")]);
+        let (net, fee) = apply_fee(&info, &FeeConfig::Fixed(coin(10, "uatom"))).unwrap();
+
+        assert_eq!(
+            fee_msg("collector", fee),
+            Some(BankMsg::Send {
+                to_address: "collector".to_string(),
+                amount: coins(10, "uatom"),
+            })
+        );
+        // keep the uatom, refund the rest
+        let refund = net.difference(&balance(coins(90, "uatom")));
+        assert_eq!(
+            refund_msg(&info, refund),
+            Some(BankMsg::Send {
+                to_address: sender.to_string(),
+                amount: coins(5, "ujuno"),
+            })
+        );
+        assert_eq!(refund_msg(&info, NativeBalance::default()), None);
+    }
+}
@@ -0,0 +1,466 @@
+//! Parsing and formatting of durations and points in time in a human-friendly way,
+//! like `"12h30m"`, `"1000 blocks"` or RFC3339 timestamps.
+
+use cosmwasm_std::Timestamp;
+use std::fmt;
+use thiserror::Error;
+
+use crate::{Duration, Expiration, Scheduled};
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Time units with their length in seconds, from the largest down
+const TIME_UNITS: [(&str, &str, u64); 5] = [
+    ("week", "weeks", 7 * SECONDS_PER_DAY),
+    ("day", "days", SECONDS_PER_DAY),
+    ("hour", "hours", 60 * 60),
+    ("minute", "minutes", 60),
+    ("second", "seconds", 1),
+];
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum TimeParseError {
+    #[error("Cannot parse an empty string")]
+    Empty {},
+
+    #[error("Expected a number in '{input}'")]
+    MissingNumber { input: String },
+
+    #[error("Missing unit after {number} in '{input}'")]
+    MissingUnit { number: u64, input: String },
+
+    #[error("Unknown unit '{unit}'")]
+    UnknownUnit { unit: String },
+
+    #[error("Cannot combine blocks and time units in '{input}'")]
+    MixedUnits { input: String },
+
+    #[error("Value out of range in '{input}'")]
+    Overflow { input: String },
+
+    #[error("Invalid RFC3339 timestamp '{input}'")]
+    InvalidTimestamp { input: String },
+
+    #[error("Cannot parse '{input}', expected {expected}")]
+    Unrecognized {
+        input: String,
+        expected: &'static str,
+    },
+}
+
+/// Parses `"7d"`, `"12h30m"`, `"in 2 hours"` or `"1000 blocks"`
+pub(crate) fn parse_duration(input: &str) -> Result<Duration, TimeParseError> {
+    let trimmed = input.trim();
+    let mut rest = trimmed.strip_prefix("in ").unwrap_or(trimmed).trim_start();
+    if rest.is_empty() {
+        return Err(TimeParseError::Empty {});
+    }
+
+    let overflow = || TimeParseError::Overflow {
+        input: input.to_string(),
+    };
+    let mut blocks: Option<u64> = None;
+    let mut seconds: Option<u64> = None;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return Err(TimeParseError::MissingNumber {
+                input: input.to_string(),
+            });
+        }
+        let number: u64 = rest[..digits].parse().map_err(|_| overflow())?;
+        rest = rest[digits..].trim_start();
+
+        let letters = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        if letters == 0 {
+            return Err(TimeParseError::MissingUnit {
+                number,
+                input: input.to_string(),
+            });
+        }
+        let unit = &rest[..letters];
+        rest = rest[letters..].trim_start();
+
+        if matches!(unit, "block" | "blocks") {
+            let total = blocks.unwrap_or_default().checked_add(number);
+            blocks = Some(total.ok_or_else(overflow)?);
+        } else {
+            let delta = number
+                .checked_mul(unit_seconds(unit)?)
+                .ok_or_else(overflow)?;
+            let total = seconds.unwrap_or_default().checked_add(delta);
+            seconds = Some(total.ok_or_else(overflow)?);
+        }
+    }
+
+    match (blocks, seconds) {
+        (Some(blocks), None) => Ok(Duration::Height(blocks)),
+        (None, Some(seconds)) => Ok(Duration::Time(seconds)),
+        _ => Err(TimeParseError::MixedUnits {
+            input: input.to_string(),
+        }),
+    }
+}
+
+fn unit_seconds(unit: &str) -> Result<u64, TimeParseError> {
+    let seconds = match unit {
+        "w" => 7 * SECONDS_PER_DAY,
+        "d" => SECONDS_PER_DAY,
+        "h" => 60 * 60,
+        "m" | "min" | "mins" => 60,
+        "s" | "sec" | "secs" => 1,
+        _ => TIME_UNITS
+            .iter()
+            .find(|(singular, plural, _)| unit == *singular || unit == *plural)
+            .map(|(_, _, seconds)| *seconds)
+            .ok_or_else(|| TimeParseError::UnknownUnit {
+                unit: unit.to_string(),
+            })?,
+    };
+    Ok(seconds)
+}
+
+/// Parses `"never"`, `"height 1000"`, an RFC3339 timestamp, or a height and a timestamp
+/// combined with `or` / `and`. A leading `"at "` is ignored.
+pub(crate) fn parse_expiration(input: &str) -> Result<Expiration, TimeParseError> {
+    let trimmed = input.trim();
+    let rest = trimmed.strip_prefix("at ").unwrap_or(trimmed).trim_start();
+    if rest.is_empty() {
+        return Err(TimeParseError::Empty {});
+    }
+    if rest == "never" {
+        return Ok(Expiration::Never {});
+    }
+
+    let combined = [(" or ", true), (" and ", false)]
+        .into_iter()
+        .find_map(|(separator, or)| rest.split_once(separator).map(|parts| (parts, or)));
+    if let Some(((first, second), or)) = combined {
+        let (height, time) = match (parse_height(first.trim()), parse_height(second.trim())) {
+            (Some(height), None) => (height?, parse_rfc3339(second.trim())?),
+            (None, Some(height)) => (height?, parse_rfc3339(first.trim())?),
+            _ => {
+                return Err(TimeParseError::Unrecognized {
+                    input: input.to_string(),
+                    expected: "a height and a RFC3339 timestamp",
+                })
+            }
+        };
+        return Ok(if or {
+            Expiration::AtHeightOrTime { height, time }
+        } else {
+            Expiration::AtHeightAndTime { height, time }
+        });
+    }
+
+    match parse_height(rest) {
+        Some(height) => Ok(Expiration::AtHeight(height?)),
+        None => Ok(Expiration::AtTime(parse_rfc3339(rest)?)),
+    }
+}
+
+/// Parses `"height 1000"` or an RFC3339 timestamp. A leading `"at "` is ignored.
+pub(crate) fn parse_scheduled(input: &str) -> Result<Scheduled, TimeParseError> {
+    match parse_expiration(input)? {
+        Expiration::AtHeight(height) => Ok(Scheduled::AtHeight(height)),
+        Expiration::AtTime(time) => Ok(Scheduled::AtTime(time)),
+        _ => Err(TimeParseError::Unrecognized {
+            input: input.to_string(),
+            expected: "a height or a RFC3339 timestamp",
+        }),
+    }
+}
+
+/// Parses `"height 1000"` or `"block 1000"`, returning None if it is not height based
+fn parse_height(input: &str) -> Option<Result<u64, TimeParseError>> {
+    let number = input
+        .strip_prefix("height ")
+        .or_else(|| input.strip_prefix("block "))?
+        .trim();
+    Some(number.parse().map_err(|_| TimeParseError::MissingNumber {
+        input: input.to_string(),
+    }))
+}
+
+/// Parses an RFC3339 timestamp like `"2024-05-01T12:00:00Z"` or
+/// `"2024-05-01T14:00:00.5+02:00"`. Times before 1970 are not supported.
+pub(crate) fn parse_rfc3339(input: &str) -> Result<Timestamp, TimeParseError> {
+    let invalid = || TimeParseError::InvalidTimestamp {
+        input: input.to_string(),
+    };
+    let bytes = input.as_bytes();
+    if bytes.len() < 20
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || !matches!(bytes[10], b'T' | b't' | b' ')
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+    {
+        return Err(invalid());
+    }
+    let number =
+        |range: std::ops::Range<usize>| input.get(range).and_then(parse_digits).ok_or_else(invalid);
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month)
+        || day == 0
+        || day > days_in_month(year, month)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return Err(invalid());
+    }
+
+    // optional fraction of a second, up to nanoseconds
+    let mut rest = input.get(19..).ok_or_else(invalid)?;
+    let mut nanos = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(fraction.len());
+        if digits == 0 || digits > 9 {
+            return Err(invalid());
+        }
+        nanos =
+            parse_digits(&fraction[..digits]).ok_or_else(invalid)? * 10u64.pow(9 - digits as u32);
+        rest = &fraction[digits..];
+    }
+
+    // offset from UTC in seconds
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match rest.as_bytes().first() {
+                Some(b'+') => 1,
+                Some(b'-') => -1,
+                _ => return Err(invalid()),
+            };
+            if rest.len() != 6 || rest.as_bytes()[3] != b':' {
+                return Err(invalid());
+            }
+            let hours = rest.get(1..3).and_then(parse_digits).ok_or_else(invalid)?;
+            let minutes = rest.get(4..6).and_then(parse_digits).ok_or_else(invalid)?;
+            if hours > 23 || minutes > 59 {
+                return Err(invalid());
+            }
+            sign * (hours * 3600 + minutes * 60) as i64
+        }
+    };
+
+    let days = days_from_civil(year, month, day);
+    let local = days * SECONDS_PER_DAY as i64 + (hour * 3600 + minute * 60 + second) as i64;
+    let utc = u64::try_from(local - offset).map_err(|_| invalid())?;
+    let utc_nanos = utc.checked_mul(NANOS_PER_SECOND).ok_or_else(invalid)?;
+    let nanos = utc_nanos.checked_add(nanos).ok_or_else(invalid)?;
+    Ok(Timestamp::from_nanos(nanos))
+}
+
+/// Displays a timestamp as RFC3339 in UTC, with as many fractional digits as needed
+pub(crate) struct Rfc3339<'a>(pub &'a Timestamp);
+
+impl fmt::Display for Rfc3339<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let time = self.0;
+        let seconds = time.seconds();
+        let (year, month, day) = civil_from_days((seconds / SECONDS_PER_DAY) as i64);
+        let of_day = seconds % SECONDS_PER_DAY;
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+            of_day / 3600,
+            of_day / 60 % 60,
+            of_day % 60
+        )?;
+        let nanos = time.subsec_nanos();
+        if nanos != 0 {
+            let fraction = format!("{nanos:09}");
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        write!(f, "Z")
+    }
+}
+
+/// Formats a duration like `"in 12 hours 30 minutes"` or `"in 1000 blocks"`
+pub(crate) fn format_duration(f: &mut fmt::Formatter, duration: &Duration) -> fmt::Result {
+    let plural = |count: u64, singular: &str, plural: &str| {
+        if count == 1 {
+            format!("1 {singular}")
+        } else {
+            format!("{count} {plural}")
+        }
+    };
+    match duration {
+        Duration::Height(height) => write!(f, "in {}", plural(*height, "block", "blocks")),
+        Duration::Time(0) => write!(f, "in 0 seconds"),
+        Duration::Time(seconds) => {
+            let mut rest = *seconds;
+            let mut parts = vec![];
+            for (singular, plurals, length) in TIME_UNITS {
+                if rest >= length {
+                    parts.push(plural(rest / length, singular, plurals));
+                    rest %= length;
+                }
+            }
+            write!(f, "in {}", parts.join(" "))
+        }
+    }
+}
+
+/// Parses a string of ASCII digits only, without sign
+fn parse_digits(digits: &str) -> Option<u64> {
+    if digits.bytes().all(|b| b.is_ascii_digit()) {
+        digits.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn is_leap_year(year: u64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar.
+/// See <http://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+fn days_from_civil(year: u64, month: u64, day: u64) -> i64 {
+    let year = year as i64 - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`
+fn civil_from_days(days: i64) -> (i64, u64, u64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u64;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u64;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(parse_duration("7d"), Ok(Duration::Time(7 * 86400)));
+        assert_eq!(parse_duration("12h30m"), Ok(Duration::Time(45000)));
+        assert_eq!(parse_duration("1w 2d"), Ok(Duration::Time(9 * 86400)));
+        assert_eq!(
+            parse_duration("in 1 hour 5 minutes 1 second"),
+            Ok(Duration::Time(3901))
+        );
+        assert_eq!(parse_duration("90s"), Ok(Duration::Time(90)));
+        assert_eq!(parse_duration("1000 blocks"), Ok(Duration::Height(1000)));
+        assert_eq!(parse_duration("1block"), Ok(Duration::Height(1)));
+
+        assert_eq!(parse_duration(" "), Err(TimeParseError::Empty {}));
+        assert_eq!(
+            parse_duration("7"),
+            Err(TimeParseError::MissingUnit {
+                number: 7,
+                input: "7".to_string()
+            })
+        );
+        assert_eq!(
+            parse_duration("d"),
+            Err(TimeParseError::MissingNumber {
+                input: "d".to_string()
+            })
+        );
+        assert_eq!(
+            parse_duration("7y"),
+            Err(TimeParseError::UnknownUnit {
+                unit: "y".to_string()
+            })
+        );
+        assert_eq!(
+            parse_duration("10 blocks 5s"),
+            Err(TimeParseError::MixedUnits {
+                input: "10 blocks 5s".to_string()
+            })
+        );
+        assert_eq!(
+            parse_duration("18446744073709551615w"),
+            Err(TimeParseError::Overflow {
+                input: "18446744073709551615w".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn rfc3339_round_trip() {
+        let cases = [
+            ("1970-01-01T00:00:00Z", 0),
+            ("2000-02-29T12:30:15Z", 951_827_415_000_000_000),
+            ("2024-05-01T12:00:00.5Z", 1_714_564_800_500_000_000),
+            ("2100-12-31T23:59:59.000000001Z", 4_133_980_799_000_000_001),
+        ];
+        for (input, nanos) in cases {
+            let time = parse_rfc3339(input).unwrap();
+            assert_eq!(time, Timestamp::from_nanos(nanos), "{input}");
+            assert_eq!(Rfc3339(&time).to_string(), input);
+        }
+
+        // offsets are converted to UTC
+        assert_eq!(
+            parse_rfc3339("2024-05-01T14:00:00+02:00").unwrap(),
+            parse_rfc3339("2024-05-01T12:00:00Z").unwrap()
+        );
+        assert_eq!(
+            parse_rfc3339("2024-05-01 07:30:00-04:30").unwrap(),
+            parse_rfc3339("2024-05-01T12:00:00Z").unwrap()
+        );
+
+        for invalid in [
+            "2024-05-01",
+            "2024-05-01T12:00:00",
+            "2023-02-29T00:00:00Z",
+            "2024-13-01T00:00:00Z",
+            "2024-05-01T24:00:00Z",
+            "2024-05-01T12:00:00.Z",
+            "2024-05-01T12:00:00+2:00",
+            "2024-05-01T12:00:00++1:00",
+            "2024-05-01T12:00:0éZ",
+            "1969-12-31T23:59:59Z",
+            "9999-12-31T23:59:59Z",
+            // just beyond the range of nanoseconds in u64
+            "2554-07-21T23:34:33.999999999Z",
+        ] {
+            assert_eq!(
+                parse_rfc3339(invalid),
+                Err(TimeParseError::InvalidTimestamp {
+                    input: invalid.to_string()
+                })
+            );
+        }
+        // the latest representable time still parses
+        parse_rfc3339("2554-07-21T23:34:33.709551615Z").unwrap();
+        // and parsing an expiration returns an error instead of panicking
+        parse_expiration("2554-07-21T23:34:33.999999999Z").unwrap_err();
+    }
+}
@@ -6,13 +6,19 @@ Types should only be added here after they are duplicated in
 a second contract, not "because we might need it"
 */
 
+mod asset;
 mod balance;
+mod block_time;
 mod expiration;
+mod fee;
+mod humanize;
 mod pagination;
 mod parse_reply;
 mod payment;
+mod recurring;
 mod scheduled;
 mod threshold;
+mod vesting;
 
 pub use pagination::{
     calc_range_end, calc_range_start, calc_range_start_string, maybe_addr, maybe_canonical,
@@ -21,9 +27,18 @@ pub use parse_reply::{
     parse_execute_response_data, parse_instantiate_response_data, MsgExecuteContractResponse,
     MsgInstantiateContractResponse, ParseReplyError,
 };
-pub use payment::{may_pay, must_pay, nonpayable, one_coin, PaymentError};
-pub use threshold::{Threshold, ThresholdError, ThresholdResponse};
+pub use payment::{
+    may_pay, may_pay_many, must_pay, must_pay_at_least, must_pay_exact, must_pay_many,
+    must_pay_range, nonpayable, one_coin, PaymentError,
+};
+pub use threshold::{Threshold, ThresholdBounds, ThresholdError, ThresholdResponse, Votes};
 
-pub use crate::balance::NativeBalance;
-pub use crate::expiration::{Duration, Expiration, DAY, HOUR, WEEK};
+pub use crate::asset::{Asset, AssetInfo, GenericBalance};
+pub use crate::balance::{bank_sends, BalanceError, NativeBalance};
+pub use crate::block_time::{BlockTimeError, BlockTimeEstimator};
+pub use crate::expiration::{Duration, Expiration, ExpirationError, DAY, HOUR, WEEK};
+pub use crate::fee::{apply_fee, fee_msg, refund_msg, FeeConfig};
+pub use crate::humanize::TimeParseError;
+pub use crate::recurring::{RecurringError, RecurringSchedule};
 pub use crate::scheduled::Scheduled;
+pub use crate::vesting::{VestingCheckpoint, VestingError, VestingSchedule};
@@ -1,6 +1,8 @@
 use cosmwasm_std::{Coin, MessageInfo, Uint256};
 use thiserror::Error;
 
+use crate::NativeBalance;
+
 /// returns an error if any coins were sent
 pub fn nonpayable(info: &MessageInfo) -> Result<(), PaymentError> {
     if info.funds.is_empty() {
@@ -11,7 +13,7 @@ pub fn nonpayable(info: &MessageInfo) -> Result<(), PaymentError> {
 }
 
 /// If exactly one coin was sent, returns it regardless of denom.
-/// Returns error if 0 or 2+ coins were sent
+/// Returns error if 0 or 2+ coins were sent, or the same denom was sent more than once
 pub fn one_coin(info: &MessageInfo) -> Result<Coin, PaymentError> {
     match info.funds.len() {
         0 => Err(PaymentError::NoFunds {}),
@@ -23,7 +25,10 @@ pub fn one_coin(info: &MessageInfo) -> Result<Coin, PaymentError> {
                 Ok(coin.clone())
             }
         }
-        _ => Err(PaymentError::MultipleDenoms {}),
+        _ => {
+            check_duplicates(info)?;
+            Err(PaymentError::MultipleDenoms {})
+        }
     }
 }
 
@@ -46,12 +51,137 @@ pub fn may_pay(info: &MessageInfo, denom: &str) -> Result<Uint256, PaymentError>
     } else if info.funds.len() == 1 && info.funds[0].denom == denom {
         Ok(info.funds[0].amount)
     } else {
+        check_duplicates(info)?;
         // find first mismatch
-        let wrong = info.funds.iter().find(|c| c.denom != denom).unwrap();
-        Err(PaymentError::ExtraDenom(wrong.denom.to_string()))
+        match info.funds.iter().find(|c| c.denom != denom) {
+            Some(wrong) => Err(PaymentError::ExtraDenom(wrong.denom.to_string())),
+            None => Ok(sent(info, denom)),
+        }
+    }
+}
+
+/// Like must_pay, but also requires the amount to be within `min` and `max`, inclusive
+pub fn must_pay_range(
+    info: &MessageInfo,
+    denom: &str,
+    min: Uint256,
+    max: Uint256,
+) -> Result<Uint256, PaymentError> {
+    let sent = must_pay(info, denom)?;
+    if sent < min {
+        Err(PaymentError::InsufficientFunds {
+            denom: denom.to_string(),
+            required: min,
+            sent,
+        })
+    } else if sent > max {
+        Err(PaymentError::ExcessFunds {
+            denom: denom.to_string(),
+            max,
+            sent,
+        })
+    } else {
+        Ok(sent)
+    }
+}
+
+/// Requires every one of the denoms to be sent with a non-zero amount, and nothing else.
+/// Returns the amounts in the order of `denoms`.
+pub fn must_pay_many(info: &MessageInfo, denoms: &[&str]) -> Result<Vec<Uint256>, PaymentError> {
+    if info.funds.is_empty() {
+        return Err(PaymentError::NoFunds {});
+    }
+    let amounts = may_pay_many(info, denoms)?;
+    match denoms
+        .iter()
+        .zip(&amounts)
+        .find(|(_, amount)| amount.is_zero())
+    {
+        Some((denom, _)) => Err(PaymentError::MissingDenom(denom.to_string())),
+        None => Ok(amounts),
+    }
+}
+
+/// Similar to must_pay_many, but any payment is optional. Returns an error if any other
+/// denom was sent. Otherwise, returns the amounts sent in the order of `denoms`, 0 if not sent.
+pub fn may_pay_many(info: &MessageInfo, denoms: &[&str]) -> Result<Vec<Uint256>, PaymentError> {
+    only_denoms(info, |denom| denoms.contains(&denom))?;
+    Ok(denoms.iter().map(|denom| sent(info, denom)).collect())
+}
+
+/// Requires exactly the given coins to be sent, no more and no less of any denom
+pub fn must_pay_exact(info: &MessageInfo, expected: &[Coin]) -> Result<(), PaymentError> {
+    only_denoms(info, |denom| expected.iter().any(|c| c.denom == denom))?;
+    for coin in expected {
+        let received = sent(info, &coin.denom);
+        if received.is_zero() && !coin.amount.is_zero() {
+            return Err(PaymentError::MissingDenom(coin.denom.clone()));
+        }
+        if received != coin.amount {
+            return Err(PaymentError::WrongAmount {
+                denom: coin.denom.clone(),
+                expected: coin.amount,
+                received,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Requires at least the required balance to be sent, and no other denoms
+pub fn must_pay_at_least(info: &MessageInfo, required: &NativeBalance) -> Result<(), PaymentError> {
+    only_denoms(info, |denom| !required.amount_of(denom).is_zero())?;
+    for coin in required.coins() {
+        let received = sent(info, &coin.denom);
+        if received.is_zero() {
+            return Err(PaymentError::MissingDenom(coin.denom.clone()));
+        }
+        if received < coin.amount {
+            return Err(PaymentError::InsufficientFunds {
+                denom: coin.denom.clone(),
+                required: coin.amount,
+                sent: received,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// returns an error for a duplicate or zero coin, or the first denom sent that is not allowed
+fn only_denoms(info: &MessageInfo, allowed: impl Fn(&str) -> bool) -> Result<(), PaymentError> {
+    check_duplicates(info)?;
+    if let Some(zero) = info.funds.iter().find(|c| c.amount.is_zero()) {
+        return Err(PaymentError::ZeroAmount {
+            denom: zero.denom.clone(),
+        });
+    }
+    match info.funds.iter().find(|c| !allowed(&c.denom)) {
+        Some(extra) => Err(PaymentError::ExtraDenom(extra.denom.clone())),
+        None => Ok(()),
     }
 }
 
+/// returns an error if any denom was sent more than once
+pub(crate) fn check_duplicates(info: &MessageInfo) -> Result<(), PaymentError> {
+    for (i, coin) in info.funds.iter().enumerate() {
+        if info.funds[..i].iter().any(|c| c.denom == coin.denom) {
+            return Err(PaymentError::DuplicateDenom {
+                denom: coin.denom.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// amount of the denom sent, denoms are checked to be unique before
+fn sent(info: &MessageInfo, denom: &str) -> Uint256 {
+    info.funds
+        .iter()
+        .find(|c| c.denom == denom)
+        .map(|c| c.amount)
+        .unwrap_or_default()
+}
+
 #[derive(Error, Debug, PartialEq, Eq)]
 pub enum PaymentError {
     #[error("Must send reserve token '{0}'")]
@@ -68,6 +198,36 @@ pub enum PaymentError {
 
     #[error("This message does no accept funds")]
     NonPayable {},
+
+    #[error("Insufficient {denom}: {required} required, {sent} sent")]
+    InsufficientFunds {
+        denom: String,
+        required: Uint256,
+        sent: Uint256,
+    },
+
+    #[error("Too much {denom}: at most {max} accepted, {sent} sent")]
+    ExcessFunds {
+        denom: String,
+        max: Uint256,
+        sent: Uint256,
+    },
+
+    #[error("Denom '{denom}' sent more than once")]
+    DuplicateDenom { denom: String },
+
+    #[error("Sent zero amount of '{denom}'")]
+    ZeroAmount { denom: String },
+
+    #[error("Fee percentage cannot exceed 100%")]
+    InvalidFeePercentage {},
+
+    #[error("Expected {expected}{denom}, received {received}{denom}")]
+    WrongAmount {
+        denom: String,
+        expected: Uint256,
+        received: Uint256,
+    },
 }
 
 #[cfg(test)]
@@ -142,4 +302,185 @@ mod test {
         let err = must_pay(&mixed_payment, atom).unwrap_err();
         assert_eq!(err, PaymentError::MultipleDenoms {});
     }
+
+    #[test]
+    fn must_pay_many_works() {
+        let deps = mock_dependencies();
+        let sender = deps.api.addr_make(SENDER);
+
+        let pair = ["uatom", "ujuno"];
+        let deposit = message_info(&sender, &[coin(20, "ujuno"), coin(10, "uatom")]);
+        let single = message_info(&sender, &coins(10, "uatom"));
+        let extra = message_info(
+            &sender,
+            &[coin(10, "uatom"), coin(20, "ujuno"), coin(1, "wei")],
+        );
+        let no_payment = message_info(&sender, &[]);
+
+        let res = must_pay_many(&deposit, &pair).unwrap();
+        assert_eq!(res, vec![Uint256::new(10), Uint256::new(20)]);
+        let res = may_pay_many(&deposit, &pair).unwrap();
+        assert_eq!(res, vec![Uint256::new(10), Uint256::new(20)]);
+
+        let err = must_pay_many(&single, &pair).unwrap_err();
+        assert_eq!(err, PaymentError::MissingDenom("ujuno".to_string()));
+        let res = may_pay_many(&single, &pair).unwrap();
+        assert_eq!(res, vec![Uint256::new(10), Uint256::zero()]);
+
+        let err = must_pay_many(&no_payment, &pair).unwrap_err();
+        assert_eq!(err, PaymentError::NoFunds {});
+        let res = may_pay_many(&no_payment, &pair).unwrap();
+        assert_eq!(res, vec![Uint256::zero(), Uint256::zero()]);
+
+        let err = must_pay_many(&extra, &pair).unwrap_err();
+        assert_eq!(err, PaymentError::ExtraDenom("wei".to_string()));
+        let err = may_pay_many(&extra, &pair).unwrap_err();
+        assert_eq!(err, PaymentError::ExtraDenom("wei".to_string()));
+    }
+
+    #[test]
+    fn must_pay_exact_works() {
+        let deps = mock_dependencies();
+        let sender = deps.api.addr_make(SENDER);
+
+        let price = [coin(10, "uatom"), coin(20, "ujuno")];
+        let exact = message_info(&sender, &[coin(20, "ujuno"), coin(10, "uatom")]);
+        let too_much = message_info(&sender, &[coin(10, "uatom"), coin(21, "ujuno")]);
+        let missing = message_info(&sender, &coins(10, "uatom"));
+        let extra = message_info(
+            &sender,
+            &[coin(10, "uatom"), coin(20, "ujuno"), coin(1, "wei")],
+        );
+
+        must_pay_exact(&exact, &price).unwrap();
+
+        let err = must_pay_exact(&too_much, &price).unwrap_err();
+        assert_eq!(
+            err,
+            PaymentError::WrongAmount {
+                denom: "ujuno".to_string(),
+                expected: Uint256::new(20),
+                received: Uint256::new(21),
+            }
+        );
+        assert_eq!(err.to_string(), "Expected 20ujuno, received 21ujuno");
+
+        let err = must_pay_exact(&missing, &price).unwrap_err();
+        assert_eq!(err, PaymentError::MissingDenom("ujuno".to_string()));
+
+        let err = must_pay_exact(&extra, &price).unwrap_err();
+        assert_eq!(err, PaymentError::ExtraDenom("wei".to_string()));
+    }
+
+    #[test]
+    fn must_pay_at_least_works() {
+        let deps = mock_dependencies();
+        let sender = deps.api.addr_make(SENDER);
+
+        let required = NativeBalance::new(vec![coin(10, "uatom"), coin(20, "ujuno")]).unwrap();
+        let exact = message_info(&sender, &[coin(10, "uatom"), coin(20, "ujuno")]);
+        let more = message_info(&sender, &[coin(15, "uatom"), coin(20, "ujuno")]);
+        let less = message_info(&sender, &[coin(9, "uatom"), coin(20, "ujuno")]);
+        let missing = message_info(&sender, &coins(10, "uatom"));
+        let extra = message_info(
+            &sender,
+            &[coin(10, "uatom"), coin(20, "ujuno"), coin(1, "wei")],
+        );
+
+        must_pay_at_least(&exact, &required).unwrap();
+        must_pay_at_least(&more, &required).unwrap();
+
+        let err = must_pay_at_least(&less, &required).unwrap_err();
+        assert_eq!(
+            err,
+            PaymentError::InsufficientFunds {
+                denom: "uatom".to_string(),
+                required: Uint256::new(10),
+                sent: Uint256::new(9),
+            }
+        );
+        assert_eq!(err.to_string(), "Insufficient uatom: 10 required, 9 sent");
+
+        let err = must_pay_at_least(&missing, &required).unwrap_err();
+        assert_eq!(err, PaymentError::MissingDenom("ujuno".to_string()));
+
+        let err = must_pay_at_least(&extra, &required).unwrap_err();
+        assert_eq!(err, PaymentError::ExtraDenom("wei".to_string()));
+    }
+
+    #[test]
+    fn duplicate_and_zero_coins() {
+        let deps = mock_dependencies();
+        let sender = deps.api.addr_make(SENDER);
+
+        let atom: &str = "uatom";
+        let duplicate = message_info(&sender, &[coin(50, atom), coin(70, atom)]);
+        let zero = message_info(&sender, &[coin(50, atom), coin(0, "ujuno")]);
+
+        let err = one_coin(&duplicate).unwrap_err();
+        assert_eq!(
+            err,
+            PaymentError::DuplicateDenom {
+                denom: atom.to_string()
+            }
+        );
+        // used to panic
+        let err = may_pay(&duplicate, atom).unwrap_err();
+        assert_eq!(
+            err,
+            PaymentError::DuplicateDenom {
+                denom: atom.to_string()
+            }
+        );
+        let err = must_pay_many(&duplicate, &[atom]).unwrap_err();
+        assert_eq!(
+            err,
+            PaymentError::DuplicateDenom {
+                denom: atom.to_string()
+            }
+        );
+
+        let err = must_pay_many(&zero, &[atom, "ujuno"]).unwrap_err();
+        assert_eq!(
+            err,
+            PaymentError::ZeroAmount {
+                denom: "ujuno".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn must_pay_range_works() {
+        let deps = mock_dependencies();
+        let sender = deps.api.addr_make(SENDER);
+
+        let atom: &str = "uatom";
+        let (min, max) = (Uint256::new(10), Uint256::new(20));
+        let pay =
+            |amount| must_pay_range(&message_info(&sender, &coins(amount, atom)), atom, min, max);
+
+        assert_eq!(pay(10).unwrap(), Uint256::new(10));
+        assert_eq!(pay(20).unwrap(), Uint256::new(20));
+        assert_eq!(
+            pay(9).unwrap_err(),
+            PaymentError::InsufficientFunds {
+                denom: atom.to_string(),
+                required: min,
+                sent: Uint256::new(9),
+            }
+        );
+        assert_eq!(
+            pay(21).unwrap_err(),
+            PaymentError::ExcessFunds {
+                denom: atom.to_string(),
+                max,
+                sent: Uint256::new(21),
+            }
+        );
+        assert_eq!(pay(0).unwrap_err(), PaymentError::NoFunds {});
+
+        let eth_payment = message_info(&sender, &coins(15, "wei"));
+        let err = must_pay_range(&eth_payment, atom, min, max).unwrap_err();
+        assert_eq!(err, PaymentError::MissingDenom(atom.to_string()));
+    }
 }
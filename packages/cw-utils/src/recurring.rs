@@ -0,0 +1,283 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{BlockInfo, Timestamp};
+use thiserror::Error;
+
+use crate::{Duration, Scheduled};
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// RecurringSchedule triggers at `start` and then again after every `every`,
+/// until `end` (exclusive) if given. All values must be either height or time based.
+///
+/// Occurrences that would lie beyond the range of `u64` can never be reached by a block
+/// and are treated as not existing.
+#[cw_serde]
+#[derive(Copy)]
+pub struct RecurringSchedule {
+    pub start: Scheduled,
+    pub every: Duration,
+    pub end: Option<Scheduled>,
+}
+
+impl RecurringSchedule {
+    /// returns error if the schedule mixes height and time, or can never repeat
+    pub fn validate(&self) -> Result<(), RecurringError> {
+        let (start, every, end) = self.positions()?;
+        if every == 0 {
+            return Err(RecurringError::ZeroInterval {});
+        }
+        match end {
+            Some(end) if end <= start => Err(RecurringError::EndBeforeStart {}),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the first occurrence that is not triggered yet at the given block,
+    /// None if there are no more
+    pub fn next_after(&self, block: &BlockInfo) -> Result<Option<Scheduled>, RecurringError> {
+        let (start, every, end) = self.valid_positions()?;
+        let now = self.position_of_block(block);
+        let next = if now < start {
+            Some(start)
+        } else {
+            ((now - start) / every)
+                .checked_add(1)
+                .and_then(|k| k.checked_mul(every))
+                .and_then(|delta| start.checked_add(delta))
+        };
+        Ok(next
+            .filter(|next| end.map_or(true, |end| *next < end))
+            .map(|next| self.scheduled_at(next)))
+    }
+
+    /// Number of occurrences after `from` up to and including `to`
+    pub fn occurrences_between(
+        &self,
+        from: &Scheduled,
+        to: &Scheduled,
+    ) -> Result<u64, RecurringError> {
+        let from = self.position_of(from)?;
+        let to = self.position_of(to)?;
+        let reached_to = self.occurrences_until(to)?;
+        let reached_from = self.occurrences_until(from)?;
+        Ok(reached_to.saturating_sub(reached_from))
+    }
+
+    /// Number of occurrences triggered after `last_run` up to the given block.
+    /// Used to catch up with runs that were missed.
+    pub fn missed_since(
+        &self,
+        last_run: &Scheduled,
+        block: &BlockInfo,
+    ) -> Result<u64, RecurringError> {
+        let now = self.scheduled_at(self.position_of_block(block));
+        self.occurrences_between(last_run, &now)
+    }
+
+    /// Number of occurrences at or before `position`
+    fn occurrences_until(&self, position: u64) -> Result<u64, RecurringError> {
+        let (start, every, end) = self.valid_positions()?;
+        if position < start {
+            return Ok(0);
+        }
+        let reached = (position - start) / every + 1;
+        Ok(match end {
+            // occurrences strictly before end
+            Some(end) => reached.min((end - start - 1) / every + 1),
+            None => reached,
+        })
+    }
+
+    fn valid_positions(&self) -> Result<(u64, u64, Option<u64>), RecurringError> {
+        self.validate()?;
+        self.positions()
+    }
+
+    /// Start, interval and end in a common unit, which is nanoseconds for time
+    fn positions(&self) -> Result<(u64, u64, Option<u64>), RecurringError> {
+        let start = self.position_of(&self.start)?;
+        let every = match (self.start, self.every) {
+            (Scheduled::AtHeight(_), Duration::Height(h)) => h,
+            (Scheduled::AtTime(_), Duration::Time(t)) => t
+                .checked_mul(NANOS_PER_SECOND)
+                .ok_or(RecurringError::Overflow {})?,
+            _ => return Err(RecurringError::MismatchedUnits {}),
+        };
+        let end = self.end.map(|end| self.position_of(&end)).transpose()?;
+        Ok((start, every, end))
+    }
+
+    fn position_of(&self, scheduled: &Scheduled) -> Result<u64, RecurringError> {
+        match (self.start, scheduled) {
+            (Scheduled::AtHeight(_), Scheduled::AtHeight(h)) => Ok(*h),
+            (Scheduled::AtTime(_), Scheduled::AtTime(t)) => Ok(t.nanos()),
+            _ => Err(RecurringError::MismatchedUnits {}),
+        }
+    }
+
+    fn position_of_block(&self, block: &BlockInfo) -> u64 {
+        match self.start {
+            Scheduled::AtHeight(_) => block.height,
+            Scheduled::AtTime(_) => block.time.nanos(),
+        }
+    }
+
+    fn scheduled_at(&self, position: u64) -> Scheduled {
+        match self.start {
+            Scheduled::AtHeight(_) => Scheduled::AtHeight(position),
+            Scheduled::AtTime(_) => Scheduled::AtTime(Timestamp::from_nanos(position)),
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum RecurringError {
+    #[error("Cannot add height and time")]
+    MismatchedUnits {},
+
+    #[error("Recurring interval cannot be zero")]
+    ZeroInterval {},
+
+    #[error("End of a recurring schedule must be after its start")]
+    EndBeforeStart {},
+
+    #[error("Recurring interval overflow")]
+    Overflow {},
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at_height(height: u64) -> BlockInfo {
+        BlockInfo {
+            height,
+            time: Timestamp::from_seconds(1_000_000),
+            chain_id: "foo".to_string(),
+        }
+    }
+
+    fn at_time(seconds: u64) -> BlockInfo {
+        BlockInfo {
+            height: 12345,
+            time: Timestamp::from_seconds(seconds),
+            chain_id: "foo".to_string(),
+        }
+    }
+
+    fn time(seconds: u64) -> Scheduled {
+        Scheduled::AtTime(Timestamp::from_seconds(seconds))
+    }
+
+    #[test]
+    fn next_occurrence() {
+        let epochs = RecurringSchedule {
+            start: Scheduled::AtHeight(100),
+            every: Duration::Height(10),
+            end: Some(Scheduled::AtHeight(130)),
+        };
+        let next = |height| epochs.next_after(&at_height(height)).unwrap();
+        assert_eq!(next(0), Some(Scheduled::AtHeight(100)));
+        assert_eq!(next(99), Some(Scheduled::AtHeight(100)));
+        assert_eq!(next(100), Some(Scheduled::AtHeight(110)));
+        assert_eq!(next(115), Some(Scheduled::AtHeight(120)));
+        // end is exclusive
+        assert_eq!(next(120), None);
+
+        let daily = RecurringSchedule {
+            start: time(1000),
+            every: Duration::Time(86400),
+            end: None,
+        };
+        assert_eq!(
+            daily.next_after(&at_time(1000 + 86400 * 3 - 1)).unwrap(),
+            Some(time(1000 + 86400 * 3))
+        );
+
+        // occurrences beyond u64 do not exist
+        let huge = RecurringSchedule {
+            start: Scheduled::AtHeight(100),
+            every: Duration::Height(u64::MAX),
+            end: None,
+        };
+        assert_eq!(huge.next_after(&at_height(100)).unwrap(), None);
+    }
+
+    #[test]
+    fn count_occurrences() {
+        let epochs = RecurringSchedule {
+            start: Scheduled::AtHeight(100),
+            every: Duration::Height(10),
+            end: Some(Scheduled::AtHeight(150)),
+        };
+        let between = |from, to| {
+            epochs
+                .occurrences_between(&Scheduled::AtHeight(from), &Scheduled::AtHeight(to))
+                .unwrap()
+        };
+        // occurrences at 100, 110, 120, 130, 140
+        assert_eq!(between(0, 99), 0);
+        assert_eq!(between(0, 100), 1);
+        assert_eq!(between(100, 100), 0);
+        assert_eq!(between(100, 135), 3);
+        assert_eq!(between(0, 1000), 5);
+        assert_eq!(between(135, 100), 0);
+
+        // catch up after missing some runs
+        let missed = epochs
+            .missed_since(&Scheduled::AtHeight(110), &at_height(131))
+            .unwrap();
+        assert_eq!(missed, 2);
+        let missed = epochs
+            .missed_since(&Scheduled::AtHeight(140), &at_height(5000))
+            .unwrap();
+        assert_eq!(missed, 0);
+    }
+
+    #[test]
+    fn invalid_schedules() {
+        let mixed = RecurringSchedule {
+            start: Scheduled::AtHeight(100),
+            every: Duration::Time(10),
+            end: None,
+        };
+        assert_eq!(mixed.validate(), Err(RecurringError::MismatchedUnits {}));
+        assert_eq!(
+            mixed.next_after(&at_height(5)),
+            Err(RecurringError::MismatchedUnits {})
+        );
+
+        let schedule = RecurringSchedule {
+            start: Scheduled::AtHeight(100),
+            every: Duration::Height(10),
+            end: None,
+        };
+        assert_eq!(
+            schedule.occurrences_between(&time(5), &Scheduled::AtHeight(200)),
+            Err(RecurringError::MismatchedUnits {})
+        );
+        assert_eq!(
+            schedule.missed_since(&time(5), &at_height(200)),
+            Err(RecurringError::MismatchedUnits {})
+        );
+
+        let zero = RecurringSchedule {
+            every: Duration::Height(0),
+            ..schedule
+        };
+        assert_eq!(zero.validate(), Err(RecurringError::ZeroInterval {}));
+
+        let ended = RecurringSchedule {
+            end: Some(Scheduled::AtHeight(100)),
+            ..schedule
+        };
+        assert_eq!(ended.validate(), Err(RecurringError::EndBeforeStart {}));
+
+        let overflow = RecurringSchedule {
+            start: time(100),
+            every: Duration::Time(u64::MAX),
+            end: None,
+        };
+        assert_eq!(overflow.validate(), Err(RecurringError::Overflow {}));
+    }
+}
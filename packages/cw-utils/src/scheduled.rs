@@ -1,9 +1,14 @@
-use crate::Duration;
+use crate::expiration::add_error;
+use crate::humanize::{parse_scheduled, Rfc3339};
+use crate::{Duration, ExpirationError, TimeParseError};
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{BlockInfo, StdError, StdResult, Timestamp};
+use cosmwasm_std::{BlockInfo, StdResult, Timestamp};
 use std::cmp::Ordering;
 use std::fmt;
 use std::ops::Add;
+use std::str::FromStr;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
 
 /// Scheduled represents a point in time when an event happens.
 /// It can compare with a BlockInfo and will return is_triggered() == true
@@ -17,8 +22,16 @@ pub enum Scheduled {
     AtTime(Timestamp),
 }
 
+/// The alternate format `{:#}` is human-friendly, like `at height 1000` or
+/// `at 2024-05-01T12:00:00Z`, and can be parsed back with `FromStr`
 impl fmt::Display for Scheduled {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            return match self {
+                Scheduled::AtHeight(height) => write!(f, "at height {height}"),
+                Scheduled::AtTime(time) => write!(f, "at {}", Rfc3339(time)),
+            };
+        }
         match self {
             Scheduled::AtHeight(height) => write!(f, "scheduled height: {height}"),
             Scheduled::AtTime(time) => write!(f, "scheduled time: {time}"),
@@ -26,27 +39,44 @@ impl fmt::Display for Scheduled {
     }
 }
 
+impl FromStr for Scheduled {
+    type Err = TimeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_scheduled(s)
+    }
+}
+
 impl Scheduled {
-    #[allow(dead_code)]
     pub fn is_triggered(&self, block: &BlockInfo) -> bool {
         match self {
             Scheduled::AtHeight(height) => block.height >= *height,
             Scheduled::AtTime(time) => block.time >= *time,
         }
     }
+
+    /// Moves the schedule later by `duration`
+    pub fn checked_add(self, duration: Duration) -> Result<Scheduled, ExpirationError> {
+        match (self, duration) {
+            (Scheduled::AtTime(t), Duration::Time(delta)) => delta
+                .checked_mul(NANOS_PER_SECOND)
+                .and_then(|delta| t.nanos().checked_add(delta))
+                .map(|nanos| Scheduled::AtTime(Timestamp::from_nanos(nanos)))
+                .ok_or(ExpirationError::Overflow {}),
+            (Scheduled::AtHeight(h), Duration::Height(delta)) => h
+                .checked_add(delta)
+                .map(Scheduled::AtHeight)
+                .ok_or(ExpirationError::Overflow {}),
+            _ => Err(ExpirationError::MismatchedUnits {}),
+        }
+    }
 }
 
 impl Add<Duration> for Scheduled {
     type Output = StdResult<Scheduled>;
 
     fn add(self, duration: Duration) -> StdResult<Scheduled> {
-        match (self, duration) {
-            (Scheduled::AtTime(t), Duration::Time(delta)) => {
-                Ok(Scheduled::AtTime(t.plus_seconds(delta)))
-            }
-            (Scheduled::AtHeight(h), Duration::Height(delta)) => Ok(Scheduled::AtHeight(h + delta)),
-            _ => Err(StdError::msg("Cannot add height and time")),
-        }
+        self.checked_add(duration).map_err(add_error)
     }
 }
 
@@ -110,6 +140,42 @@ mod test {
 
         // mismatched
         let end = Scheduled::AtHeight(12345) + Duration::Time(1500);
+        assert!(end
+            .unwrap_err()
+            .to_string()
+            .contains("Cannot add height and time"));
+
+        // overflow is an error rather than a panic
+        let end = Scheduled::AtHeight(u64::MAX) + Duration::Height(1);
         end.unwrap_err();
+        assert_eq!(
+            Scheduled::AtTime(Timestamp::from_seconds(1)).checked_add(Duration::Time(u64::MAX)),
+            Err(ExpirationError::Overflow {})
+        );
+        assert_eq!(
+            Scheduled::AtHeight(5).checked_add(Duration::Height(7)),
+            Ok(Scheduled::AtHeight(12))
+        );
+    }
+
+    #[test]
+    fn parse_and_humanize() {
+        let scheduled: Scheduled = "height 1000".parse().unwrap();
+        assert_eq!(scheduled, Scheduled::AtHeight(1000));
+        assert_eq!(format!("{scheduled:#}"), "at height 1000");
+
+        let scheduled: Scheduled = "at 2024-05-01T12:00:00.25Z".parse().unwrap();
+        assert_eq!(
+            scheduled,
+            Scheduled::AtTime(Timestamp::from_nanos(1_714_564_800_250_000_000))
+        );
+        assert_eq!(format!("{scheduled:#}"), "at 2024-05-01T12:00:00.25Z");
+        assert_eq!(
+            scheduled.to_string(),
+            "scheduled time: 1714564800.250000000"
+        );
+
+        let err = "never".parse::<Scheduled>().unwrap_err();
+        assert!(matches!(err, TimeParseError::Unrecognized { .. }));
     }
 }
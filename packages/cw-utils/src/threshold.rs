@@ -1,7 +1,40 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Decimal, StdError};
+use cosmwasm_std::{Decimal, StdError, Uint256, Uint512};
+use serde::de::{self, Deserializer, Visitor};
+use std::fmt;
 use thiserror::Error;
 
+// we multiply by this when calculating needed_votes in order to round up properly
+// Note: `10u128.pow(9)` fails as "u128::pow` is not yet stable as a const fn"
+const PRECISION_FACTOR: u128 = 1_000_000_000;
+
+/// The weights of all votes cast on a proposal, by option
+#[cw_serde]
+#[derive(Default, Copy)]
+#[schemaifier(mute_warnings)]
+pub struct Votes {
+    #[serde(deserialize_with = "deserialize_weight")]
+    pub yes: Uint256,
+    #[serde(deserialize_with = "deserialize_weight")]
+    pub no: Uint256,
+    #[serde(deserialize_with = "deserialize_weight")]
+    pub abstain: Uint256,
+    #[serde(deserialize_with = "deserialize_weight")]
+    pub veto: Uint256,
+}
+
+impl Votes {
+    /// sum of all votes
+    pub fn total(&self) -> Uint256 {
+        self.yes + self.no + self.abstain + self.veto
+    }
+
+    /// weight of the votes against the proposal, veto counts as no
+    pub fn against(&self) -> Uint256 {
+        self.no + self.veto
+    }
+}
+
 /// This defines the different ways tallies can happen.
 ///
 /// The total_weight used for calculating success as well as the weights of each
@@ -10,10 +43,14 @@ use thiserror::Error;
 /// correct cw4 implementation).
 /// See also `ThresholdResponse` in the cw3 spec.
 #[cw_serde]
+#[schemaifier(mute_warnings)]
 pub enum Threshold {
     /// Declares that a fixed weight of Yes votes is needed to pass.
     /// See `ThresholdResponse.AbsoluteCount` in the cw3 spec for details.
-    AbsoluteCount { weight: u64 },
+    AbsoluteCount {
+        #[serde(deserialize_with = "deserialize_weight")]
+        weight: Uint256,
+    },
 
     /// Declares a percentage of the total weight that must cast Yes votes in order for
     /// a proposal to pass.
@@ -24,17 +61,78 @@ pub enum Threshold {
     /// for the vote to be considered at all.
     /// See `ThresholdResponse.ThresholdQuorum` in the cw3 spec for details.
     ThresholdQuorum { threshold: Decimal, quorum: Decimal },
+
+    /// Like `ThresholdQuorum`, but the proposal is rejected if more than `veto_threshold`
+    /// of the votes cast are vetoes.
+    /// See `ThresholdResponse.ThresholdQuorumVeto` in the cw3 spec for details.
+    ThresholdQuorumVeto {
+        threshold: Decimal,
+        quorum: Decimal,
+        veto_threshold: Decimal,
+    },
+}
+
+/// The range of percentages accepted by `Threshold::validate_with`.
+/// A quorum of zero is never accepted, independently of `min_quorum`.
+#[cw_serde]
+#[derive(Copy)]
+pub struct ThresholdBounds {
+    pub min_threshold: Decimal,
+    pub max_threshold: Decimal,
+    pub min_quorum: Decimal,
+}
+
+impl Default for ThresholdBounds {
+    /// A majority is required to pass, any positive quorum is fine
+    fn default() -> Self {
+        ThresholdBounds {
+            min_threshold: Decimal::percent(50),
+            max_threshold: Decimal::one(),
+            min_quorum: Decimal::zero(),
+        }
+    }
 }
 
 impl Threshold {
     /// returns error if this is an unreachable value,
     /// given a total weight of all members in the group
-    pub fn validate(&self, total_weight: u64) -> Result<(), ThresholdError> {
+    pub fn validate(&self, total_weight: impl Into<Uint256>) -> Result<(), ThresholdError> {
+        self.validate_percentages(total_weight, valid_threshold, valid_quorum)
+    }
+
+    /// Like `validate`, but with custom bounds for the percentages,
+    /// e.g. to allow a minority threshold
+    pub fn validate_with(
+        &self,
+        total_weight: impl Into<Uint256>,
+        bounds: ThresholdBounds,
+    ) -> Result<(), ThresholdError> {
+        if bounds.min_threshold.is_zero()
+            || bounds.min_threshold > bounds.max_threshold
+            || bounds.max_threshold > Decimal::one()
+            || bounds.min_quorum > Decimal::one()
+        {
+            return Err(ThresholdError::InvalidBounds {});
+        }
+        self.validate_percentages(
+            total_weight,
+            |percent| valid_threshold_within(percent, &bounds),
+            |percent| valid_quorum_within(percent, &bounds),
+        )
+    }
+
+    fn validate_percentages(
+        &self,
+        total_weight: impl Into<Uint256>,
+        valid_threshold: impl Fn(&Decimal) -> Result<(), ThresholdError>,
+        valid_quorum: impl Fn(&Decimal) -> Result<(), ThresholdError>,
+    ) -> Result<(), ThresholdError> {
+        let total_weight = total_weight.into();
         match self {
             Threshold::AbsoluteCount {
                 weight: weight_needed,
             } => {
-                if *weight_needed == 0 {
+                if weight_needed.is_zero() {
                     Err(ThresholdError::ZeroWeight {})
                 } else if *weight_needed > total_weight {
                     Err(ThresholdError::UnreachableWeight {})
@@ -52,11 +150,21 @@ impl Threshold {
                 valid_threshold(threshold)?;
                 valid_quorum(quroum)
             }
+            Threshold::ThresholdQuorumVeto {
+                threshold,
+                quorum,
+                veto_threshold,
+            } => {
+                valid_threshold(threshold)?;
+                valid_quorum(quorum)?;
+                valid_veto_threshold(veto_threshold)
+            }
         }
     }
 
     /// Creates a response from the saved data, just missing the total_weight info
-    pub fn to_response(&self, total_weight: u64) -> ThresholdResponse {
+    pub fn to_response(&self, total_weight: impl Into<Uint256>) -> ThresholdResponse {
+        let total_weight = total_weight.into();
         match self.clone() {
             Threshold::AbsoluteCount { weight } => ThresholdResponse::AbsoluteCount {
                 weight,
@@ -73,8 +181,179 @@ impl Threshold {
                     total_weight,
                 }
             }
+            Threshold::ThresholdQuorumVeto {
+                threshold,
+                quorum,
+                veto_threshold,
+            } => ThresholdResponse::ThresholdQuorumVeto {
+                threshold,
+                quorum,
+                veto_threshold,
+                total_weight,
+            },
         }
     }
+
+    /// Returns true if the votes are enough to pass a proposal, given the total weight
+    /// of all voters. Before expiration, we assume all missing votes will be cast against.
+    pub fn is_passed(
+        &self,
+        votes: &Votes,
+        total_weight: impl Into<Uint256>,
+        expired: bool,
+    ) -> bool {
+        let total_weight = total_weight.into();
+        match self {
+            Threshold::AbsoluteCount {
+                weight: weight_needed,
+            } => votes.yes >= *weight_needed,
+            Threshold::AbsolutePercentage {
+                percentage: percentage_needed,
+            } => {
+                votes.yes
+                    >= votes_needed(
+                        total_weight.saturating_sub(votes.abstain),
+                        percentage_needed,
+                    )
+            }
+            Threshold::ThresholdQuorum { threshold, quorum } => {
+                // we always require the quorum
+                if votes.total() < votes_needed(total_weight, quorum) {
+                    return false;
+                }
+                if expired {
+                    // If expired, we compare vote_count against the total number of votes (minus abstain).
+                    let opinions = votes.total() - votes.abstain;
+                    votes.yes >= votes_needed(opinions, threshold)
+                } else {
+                    // If not expired, we must assume all non-votes will be cast against
+                    let possible_opinions = total_weight.saturating_sub(votes.abstain);
+                    votes.yes >= votes_needed(possible_opinions, threshold)
+                }
+            }
+            Threshold::ThresholdQuorumVeto {
+                threshold,
+                quorum,
+                veto_threshold,
+            } => {
+                if votes.total() < votes_needed(total_weight, quorum) {
+                    return false;
+                }
+                if expired {
+                    let opinions = votes.total() - votes.abstain;
+                    !is_vetoed(votes.veto, votes.total(), veto_threshold)
+                        && votes.yes >= votes_needed(opinions, threshold)
+                } else {
+                    // If not expired, we must assume all non-votes will be cast as veto
+                    let missing = total_weight.saturating_sub(votes.total());
+                    let possible_opinions = total_weight.saturating_sub(votes.abstain);
+                    !is_vetoed(votes.veto + missing, total_weight, veto_threshold)
+                        && votes.yes >= votes_needed(possible_opinions, threshold)
+                }
+            }
+        }
+    }
+
+    /// Returns true if the votes are sure to reject a proposal (even before expiration,
+    /// if no future sequence of possible votes could cause it to pass).
+    /// Before expiration, we assume all missing votes will be cast in favor.
+    pub fn is_rejected(
+        &self,
+        votes: &Votes,
+        total_weight: impl Into<Uint256>,
+        expired: bool,
+    ) -> bool {
+        let total_weight = total_weight.into();
+        match self {
+            Threshold::AbsoluteCount {
+                weight: weight_needed,
+            } => {
+                let weight = total_weight.saturating_sub(*weight_needed);
+                votes.against() > weight
+            }
+            Threshold::AbsolutePercentage {
+                percentage: percentage_needed,
+            } => {
+                votes.against()
+                    > votes_needed(
+                        total_weight.saturating_sub(votes.abstain),
+                        &(Decimal::one() - percentage_needed),
+                    )
+            }
+            Threshold::ThresholdQuorum {
+                threshold,
+                quorum: _,
+            } => {
+                if expired {
+                    // If expired, we compare vote_count against the total number of votes (minus abstain).
+                    let opinions = votes.total() - votes.abstain;
+                    votes.against() > votes_needed(opinions, &(Decimal::one() - threshold))
+                } else {
+                    // If not expired, we must assume all non-votes will be cast for
+                    let possible_opinions = total_weight.saturating_sub(votes.abstain);
+                    votes.against() > votes_needed(possible_opinions, &(Decimal::one() - threshold))
+                }
+            }
+            Threshold::ThresholdQuorumVeto {
+                threshold,
+                quorum: _,
+                veto_threshold,
+            } => {
+                if expired {
+                    let opinions = votes.total() - votes.abstain;
+                    is_vetoed(votes.veto, votes.total(), veto_threshold)
+                        || votes.against() > votes_needed(opinions, &(Decimal::one() - threshold))
+                } else {
+                    // If not expired, we must assume all non-votes will be cast for.
+                    // The veto share can then only shrink, down to a share of the total weight.
+                    let possible_opinions = total_weight.saturating_sub(votes.abstain);
+                    is_vetoed(votes.veto, total_weight, veto_threshold)
+                        || votes.against()
+                            > votes_needed(possible_opinions, &(Decimal::one() - threshold))
+                }
+            }
+        }
+    }
+}
+
+// this is a helper function so Decimal works with Uint256 weights without overflow
+// also, we must *round up* here, as we need 8, not 7 votes to reach 50% of 15 total
+fn votes_needed(weight: Uint256, percentage: &Decimal) -> Uint256 {
+    let applied = weight.full_mul(PRECISION_FACTOR) * Uint512::from(percentage.atomics())
+        / Uint512::from(Decimal::one().atomics());
+    // Divide by PRECISION_FACTOR, rounding up to the nearest integer
+    let precision = Uint512::from(PRECISION_FACTOR);
+    let needed = (applied + precision - Uint512::one()) / precision;
+    Uint256::try_from(needed).unwrap_or(Uint256::MAX)
+}
+
+// a proposal is vetoed if strictly more than `veto_threshold` of the votes cast are vetoes
+fn is_vetoed(veto: Uint256, cast: Uint256, veto_threshold: &Decimal) -> bool {
+    veto.full_mul(Decimal::one().atomics()) > cast.full_mul(veto_threshold.atomics())
+}
+
+/// Weights used to be u64 and serialized as JSON numbers, so those are still accepted
+/// next to the string representation of `Uint256`
+fn deserialize_weight<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uint256, D::Error> {
+    struct WeightVisitor;
+
+    impl Visitor<'_> for WeightVisitor {
+        type Value = Uint256;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a weight as unsigned integer or string-encoded Uint256")
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<Uint256, E> {
+            Ok(Uint256::from(v))
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Uint256, E> {
+            v.parse().map_err(E::custom)
+        }
+    }
+
+    deserializer.deserialize_any(WeightVisitor)
 }
 
 /// Asserts that the 0.5 < percent <= 1.0
@@ -97,6 +376,44 @@ fn valid_quorum(percent: &Decimal) -> Result<(), ThresholdError> {
     }
 }
 
+/// Asserts that min_threshold <= percent <= max_threshold
+fn valid_threshold_within(
+    percent: &Decimal,
+    bounds: &ThresholdBounds,
+) -> Result<(), ThresholdError> {
+    if *percent > bounds.max_threshold || *percent < bounds.min_threshold {
+        Err(ThresholdError::ThresholdOutOfBounds {
+            min: bounds.min_threshold,
+            max: bounds.max_threshold,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Asserts that min_quorum <= percent <= 1.0, and percent > 0
+fn valid_quorum_within(percent: &Decimal, bounds: &ThresholdBounds) -> Result<(), ThresholdError> {
+    valid_quorum(percent)?;
+    if *percent < bounds.min_quorum {
+        Err(ThresholdError::QuorumTooLow {
+            min: bounds.min_quorum,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Asserts that 0 < percent <= 1.0
+fn valid_veto_threshold(percent: &Decimal) -> Result<(), ThresholdError> {
+    if percent.is_zero() {
+        Err(ThresholdError::ZeroVetoThreshold {})
+    } else if *percent > Decimal::one() {
+        Err(ThresholdError::UnreachableVetoThreshold {})
+    } else {
+        Ok(())
+    }
+}
+
 /// This defines the different ways tallies can happen.
 /// Every contract should support a subset of these, ideally all.
 ///
@@ -105,6 +422,7 @@ fn valid_quorum(percent: &Decimal) -> Result<(), ThresholdError> {
 /// the block at which the proposal starts (this is likely the responsibility of a
 /// correct cw4 implementation).
 #[cw_serde]
+#[schemaifier(mute_warnings)]
 pub enum ThresholdResponse {
     /// Declares that a fixed weight of yes votes is needed to pass.
     /// It does not matter how many no votes are cast, or how many do not vote,
@@ -114,7 +432,12 @@ pub enum ThresholdResponse {
     /// like 3 of 5. (weight: 3, total_weight: 5)
     ///
     /// A proposal of this type can pass early as soon as the needed weight of yes votes has been cast.
-    AbsoluteCount { weight: u64, total_weight: u64 },
+    AbsoluteCount {
+        #[serde(deserialize_with = "deserialize_weight")]
+        weight: Uint256,
+        #[serde(deserialize_with = "deserialize_weight")]
+        total_weight: Uint256,
+    },
 
     /// Declares a percentage of the total weight that must cast Yes votes, in order for
     /// a proposal to pass. The passing weight is computed over the total weight minus the weight of the
@@ -134,7 +457,8 @@ pub enum ThresholdResponse {
     /// require 5 Yes of 9 to pass, rather than 3 yes of 9 as would be the case with `AbsoluteCount`.
     AbsolutePercentage {
         percentage: Decimal,
-        total_weight: u64,
+        #[serde(deserialize_with = "deserialize_weight")]
+        total_weight: Uint256,
     },
 
     /// In addition to a `threshold`, declares a `quorum` of the total votes that must participate
@@ -161,7 +485,27 @@ pub enum ThresholdResponse {
     ThresholdQuorum {
         threshold: Decimal,
         quorum: Decimal,
-        total_weight: u64,
+        #[serde(deserialize_with = "deserialize_weight")]
+        total_weight: Uint256,
+    },
+
+    /// Extends `ThresholdQuorum` with a `veto_threshold`, as used by Cosmos SDK governance.
+    /// If strictly more than `veto_threshold` of the votes cast (including Abstain) are Veto,
+    /// the proposal is rejected, independently of the Yes votes. Otherwise it is tallied like
+    /// `ThresholdQuorum`, where Veto votes count as No.
+    ///
+    /// Supermajority rules, e.g. for constitutional changes, are expressed with a `threshold`
+    /// above 50%, like 66.7%.
+    ///
+    /// A proposal of this type can only pass early if it would still pass when all remaining
+    /// weight cast Veto. It is rejected early as soon as the Veto votes exceed `veto_threshold`
+    /// of the total weight.
+    ThresholdQuorumVeto {
+        threshold: Decimal,
+        quorum: Decimal,
+        veto_threshold: Decimal,
+        #[serde(deserialize_with = "deserialize_weight")]
+        total_weight: Uint256,
     },
 }
 
@@ -173,12 +517,27 @@ pub enum ThresholdError {
     #[error("Invalid voting threshold percentage, must be in the 0.5-1.0 range")]
     InvalidThreshold {},
 
+    #[error("Invalid voting threshold percentage, must be in the {min}-{max} range")]
+    ThresholdOutOfBounds { min: Decimal, max: Decimal },
+
+    #[error("Invalid threshold bounds, must satisfy 0 < min_threshold <= max_threshold <= 1 and min_quorum <= 1")]
+    InvalidBounds {},
+
+    #[error("Required quorum threshold must be at least {min}")]
+    QuorumTooLow { min: Decimal },
+
     #[error("Required quorum threshold cannot be zero")]
     ZeroQuorumThreshold {},
 
     #[error("Not possible to reach required quorum threshold")]
     UnreachableQuorumThreshold {},
 
+    #[error("Veto threshold cannot be zero")]
+    ZeroVetoThreshold {},
+
+    #[error("Not possible to reach veto threshold")]
+    UnreachableVetoThreshold {},
+
     #[error("Required weight cannot be zero")]
     ZeroWeight {},
 
@@ -189,6 +548,7 @@ pub enum ThresholdError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cosmwasm_std::{from_json, to_json_vec, Uint128};
 
     #[test]
     fn validate_quorum_percentage() {
@@ -234,27 +594,39 @@ mod tests {
     #[test]
     fn validate_threshold() {
         // absolute count ensures 0 < required <= total_weight
-        let err = Threshold::AbsoluteCount { weight: 0 }
-            .validate(5)
-            .unwrap_err();
+        let err = Threshold::AbsoluteCount {
+            weight: Uint256::new(0),
+        }
+        .validate(5u64)
+        .unwrap_err();
         // TODO: remove to_string() when PartialEq implemented
         assert_eq!(err.to_string(), ThresholdError::ZeroWeight {}.to_string());
-        let err = Threshold::AbsoluteCount { weight: 6 }
-            .validate(5)
-            .unwrap_err();
+        let err = Threshold::AbsoluteCount {
+            weight: Uint256::new(6),
+        }
+        .validate(5u64)
+        .unwrap_err();
         assert_eq!(
             err.to_string(),
             ThresholdError::UnreachableWeight {}.to_string()
         );
 
-        Threshold::AbsoluteCount { weight: 1 }.validate(5).unwrap();
-        Threshold::AbsoluteCount { weight: 5 }.validate(5).unwrap();
+        Threshold::AbsoluteCount {
+            weight: Uint256::new(1),
+        }
+        .validate(5u64)
+        .unwrap();
+        Threshold::AbsoluteCount {
+            weight: Uint256::new(5),
+        }
+        .validate(5u64)
+        .unwrap();
 
         // AbsolutePercentage just enforces valid_percentage (tested above)
         let err = Threshold::AbsolutePercentage {
             percentage: Decimal::zero(),
         }
-        .validate(5)
+        .validate(5u64)
         .unwrap_err();
         assert_eq!(
             err.to_string(),
@@ -263,7 +635,7 @@ mod tests {
         Threshold::AbsolutePercentage {
             percentage: Decimal::percent(51),
         }
-        .validate(5)
+        .validate(5u64)
         .unwrap();
 
         // Quorum enforces both valid just enforces valid_percentage (tested above)
@@ -271,13 +643,13 @@ mod tests {
             threshold: Decimal::percent(51),
             quorum: Decimal::percent(40),
         }
-        .validate(5)
+        .validate(5u64)
         .unwrap();
         let err = Threshold::ThresholdQuorum {
             threshold: Decimal::percent(101),
             quorum: Decimal::percent(40),
         }
-        .validate(5)
+        .validate(5u64)
         .unwrap_err();
         assert_eq!(
             err.to_string(),
@@ -287,23 +659,151 @@ mod tests {
             threshold: Decimal::percent(51),
             quorum: Decimal::percent(0),
         }
-        .validate(5)
+        .validate(5u64)
         .unwrap_err();
         assert_eq!(
             err.to_string(),
             ThresholdError::ZeroQuorumThreshold {}.to_string()
         );
+
+        // veto threshold ensures 0 < veto_threshold <= 1
+        let veto = |veto_threshold| Threshold::ThresholdQuorumVeto {
+            threshold: Decimal::permille(667),
+            quorum: Decimal::permille(334),
+            veto_threshold,
+        };
+        veto(Decimal::permille(334)).validate(5u64).unwrap();
+        veto(Decimal::one()).validate(5u64).unwrap();
+        let err = veto(Decimal::zero()).validate(5u64).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ThresholdError::ZeroVetoThreshold {}.to_string()
+        );
+        let err = veto(Decimal::permille(1001)).validate(5u64).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ThresholdError::UnreachableVetoThreshold {}.to_string()
+        );
+        // as well as the threshold and quorum
+        let err = Threshold::ThresholdQuorumVeto {
+            threshold: Decimal::percent(49),
+            quorum: Decimal::percent(40),
+            veto_threshold: Decimal::permille(334),
+        }
+        .validate(5u64)
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ThresholdError::InvalidThreshold {}.to_string()
+        );
+        let err = Threshold::ThresholdQuorumVeto {
+            threshold: Decimal::percent(50),
+            quorum: Decimal::percent(101),
+            veto_threshold: Decimal::permille(334),
+        }
+        .validate(5u64)
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ThresholdError::UnreachableQuorumThreshold {}.to_string()
+        );
+    }
+
+    #[test]
+    fn validate_with_custom_bounds() {
+        let signalling = Threshold::ThresholdQuorum {
+            threshold: Decimal::percent(33),
+            quorum: Decimal::percent(10),
+        };
+        // rejected by the defaults
+        let err = signalling.validate(5u64).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid voting threshold percentage, must be in the 0.5-1.0 range"
+        );
+        let err = signalling
+            .validate_with(5u64, ThresholdBounds::default())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ThresholdError::ThresholdOutOfBounds { min, max }
+                if min == Decimal::percent(50) && max == Decimal::one()
+        ));
+
+        let minority = ThresholdBounds {
+            min_threshold: Decimal::percent(25),
+            max_threshold: Decimal::percent(75),
+            min_quorum: Decimal::percent(10),
+        };
+        signalling.validate_with(5u64, minority).unwrap();
+
+        let err = Threshold::AbsolutePercentage {
+            percentage: Decimal::percent(80),
+        }
+        .validate_with(5u64, minority)
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ThresholdError::ThresholdOutOfBounds { min, max }
+                if min == Decimal::percent(25) && max == Decimal::percent(75)
+        ));
+
+        let err = Threshold::ThresholdQuorumVeto {
+            threshold: Decimal::percent(33),
+            quorum: Decimal::percent(5),
+            veto_threshold: Decimal::percent(33),
+        }
+        .validate_with(5u64, minority)
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ThresholdError::QuorumTooLow { min } if min == Decimal::percent(10)
+        ));
+
+        // absolute counts are not affected by the bounds
+        Threshold::AbsoluteCount {
+            weight: Uint256::new(1),
+        }
+        .validate_with(5u64, minority)
+        .unwrap();
+
+        // the bounds themselves must make sense
+        let invalid = [
+            ThresholdBounds {
+                min_threshold: Decimal::zero(),
+                ..minority
+            },
+            ThresholdBounds {
+                min_threshold: Decimal::percent(80),
+                ..minority
+            },
+            ThresholdBounds {
+                max_threshold: Decimal::percent(101),
+                ..minority
+            },
+            ThresholdBounds {
+                min_quorum: Decimal::percent(101),
+                ..minority
+            },
+        ];
+        for bounds in invalid {
+            let err = signalling.validate_with(5u64, bounds).unwrap_err();
+            assert!(matches!(err, ThresholdError::InvalidBounds {}));
+        }
     }
 
     #[test]
     fn threshold_response() {
-        let total_weight: u64 = 100;
+        let total_weight = Uint256::new(100);
 
-        let res = Threshold::AbsoluteCount { weight: 42 }.to_response(total_weight);
+        let res = Threshold::AbsoluteCount {
+            weight: Uint256::new(42),
+        }
+        .to_response(total_weight);
         assert_eq!(
             res,
             ThresholdResponse::AbsoluteCount {
-                weight: 42,
+                weight: Uint256::new(42),
                 total_weight
             }
         );
@@ -333,5 +833,389 @@ mod tests {
                 total_weight
             }
         );
+
+        let res = Threshold::ThresholdQuorumVeto {
+            threshold: Decimal::percent(50),
+            quorum: Decimal::permille(334),
+            veto_threshold: Decimal::permille(334),
+        }
+        .to_response(total_weight);
+        assert_eq!(
+            res,
+            ThresholdResponse::ThresholdQuorumVeto {
+                threshold: Decimal::percent(50),
+                quorum: Decimal::permille(334),
+                veto_threshold: Decimal::permille(334),
+                total_weight
+            }
+        );
+    }
+
+    fn votes(yes: u64, no: u64, abstain: u64, veto: u64) -> Votes {
+        Votes {
+            yes: yes.into(),
+            no: no.into(),
+            abstain: abstain.into(),
+            veto: veto.into(),
+        }
+    }
+
+    #[test]
+    fn votes_needed_rounds_up() {
+        assert_eq!(
+            votes_needed(Uint256::new(15), &Decimal::percent(50)),
+            Uint256::new(8)
+        );
+        assert_eq!(
+            votes_needed(Uint256::new(10), &Decimal::percent(50)),
+            Uint256::new(5)
+        );
+        assert_eq!(
+            votes_needed(Uint256::new(3), &Decimal::percent(66)),
+            Uint256::new(2)
+        );
+        assert_eq!(
+            votes_needed(Uint256::new(3), &Decimal::percent(67)),
+            Uint256::new(3)
+        );
+        assert_eq!(
+            votes_needed(Uint256::new(0), &Decimal::percent(67)),
+            Uint256::new(0)
+        );
+        assert_eq!(
+            votes_needed(Uint256::new(7), &Decimal::one()),
+            Uint256::new(7)
+        );
+        assert_eq!(
+            votes_needed(Uint256::new(7), &Decimal::zero()),
+            Uint256::new(0)
+        );
+    }
+
+    #[test]
+    fn votes_total() {
+        let tally = votes(1, 2, 3, 4);
+        assert_eq!(tally.total(), Uint256::new(10));
+        assert_eq!(tally.against(), Uint256::new(6));
+        assert_eq!(Votes::default().total(), Uint256::zero());
+    }
+
+    #[test]
+    fn passed_absolute_count() {
+        let fixed = Threshold::AbsoluteCount {
+            weight: Uint256::new(10),
+        };
+        // same expired or not, regardless of other votes
+        let tally = votes(7, 0, 0, 4);
+        assert!(!fixed.is_passed(&tally, 30u64, false));
+        assert!(!fixed.is_passed(&tally, 30u64, true));
+        let tally = votes(10, 12, 3, 4);
+        assert!(fixed.is_passed(&tally, 30u64, false));
+        assert!(fixed.is_passed(&tally, 30u64, true));
+    }
+
+    #[test]
+    fn rejected_absolute_count() {
+        let fixed = Threshold::AbsoluteCount {
+            weight: Uint256::new(10),
+        };
+        // 20 against still leaves 10 possible yes votes
+        let tally = votes(0, 15, 0, 5);
+        assert!(!fixed.is_rejected(&tally, 30u64, false));
+        assert!(!fixed.is_rejected(&tally, 30u64, true));
+        // one more and it is impossible to pass
+        let tally = votes(0, 16, 0, 5);
+        assert!(fixed.is_rejected(&tally, 30u64, false));
+        assert!(fixed.is_rejected(&tally, 30u64, true));
+        // abstain does not count against
+        let tally = votes(0, 15, 10, 5);
+        assert!(!fixed.is_rejected(&tally, 30u64, false));
+    }
+
+    #[test]
+    fn passed_absolute_percentage() {
+        let percent = Threshold::AbsolutePercentage {
+            percentage: Decimal::percent(50),
+        };
+        // 8 of 15 needed
+        let tally = votes(7, 0, 0, 0);
+        assert!(!percent.is_passed(&tally, 15u64, false));
+        assert!(!percent.is_passed(&tally, 15u64, true));
+        let tally = votes(8, 0, 0, 0);
+        assert!(percent.is_passed(&tally, 15u64, false));
+        assert!(percent.is_passed(&tally, 15u64, true));
+        // abstain is removed from the total: 6 of 12 needed
+        let tally = votes(6, 3, 3, 0);
+        assert!(percent.is_passed(&tally, 15u64, false));
+        let tally = votes(5, 3, 3, 0);
+        assert!(!percent.is_passed(&tally, 15u64, true));
+    }
+
+    #[test]
+    fn rejected_absolute_percentage() {
+        let percent = Threshold::AbsolutePercentage {
+            percentage: Decimal::percent(60),
+        };
+        // 6 yes of 10 needed, 5 against leaves only 5 possible
+        let tally = votes(0, 4, 0, 0);
+        assert!(!percent.is_rejected(&tally, 10u64, false));
+        assert!(!percent.is_rejected(&tally, 10u64, true));
+        let tally = votes(0, 3, 0, 2);
+        assert!(percent.is_rejected(&tally, 10u64, false));
+        assert!(percent.is_rejected(&tally, 10u64, true));
+        // with 5 abstaining, 2 against leave 3 possible, still enough
+        let tally = votes(0, 2, 5, 0);
+        assert!(!percent.is_rejected(&tally, 10u64, false));
+        let tally = votes(0, 3, 5, 0);
+        assert!(percent.is_rejected(&tally, 10u64, false));
+
+        // rounding is conservative: 8 against of 15 makes 50% impossible, but
+        // rejection needs more than the rounded up 8
+        let percent = Threshold::AbsolutePercentage {
+            percentage: Decimal::percent(50),
+        };
+        let tally = votes(0, 8, 0, 0);
+        assert!(!percent.is_rejected(&tally, 15u64, false));
+        assert!(!percent.is_passed(&tally, 15u64, true));
+        let tally = votes(0, 9, 0, 0);
+        assert!(percent.is_rejected(&tally, 15u64, false));
+    }
+
+    #[test]
+    fn passed_threshold_quorum() {
+        let quorum = Threshold::ThresholdQuorum {
+            threshold: Decimal::percent(50),
+            quorum: Decimal::percent(40),
+        };
+        // quorum reached, but not enough to pass early
+        let tally = votes(30, 10, 0, 0);
+        assert!(!quorum.is_passed(&tally, 100u64, false));
+        // when expired, only cast votes count
+        assert!(quorum.is_passed(&tally, 100u64, true));
+
+        // quorum is always required
+        let tally = votes(35, 0, 0, 0);
+        assert!(!quorum.is_passed(&tally, 100u64, false));
+        assert!(!quorum.is_passed(&tally, 100u64, true));
+        // abstain counts towards quorum, but not towards threshold
+        let tally = votes(35, 0, 5, 0);
+        assert!(quorum.is_passed(&tally, 100u64, true));
+        assert!(!quorum.is_passed(&tally, 100u64, false));
+
+        // passes early with a majority of all possible votes
+        let tally = votes(48, 10, 4, 0);
+        assert!(quorum.is_passed(&tally, 100u64, false));
+        // veto counts as an opinion against
+        let tally = votes(20, 0, 0, 21);
+        assert!(!quorum.is_passed(&tally, 100u64, true));
+    }
+
+    #[test]
+    fn passed_threshold_quorum_documented_example() {
+        // 30% Yes votes, 10% No votes, and 20% Abstain
+        let tally = votes(30, 10, 20, 0);
+        let early = |threshold, quorum| Threshold::ThresholdQuorum { threshold, quorum };
+
+        // would pass early if quorum <= 60% and threshold <= 37.5%
+        assert!(
+            early(Decimal::permille(375), Decimal::percent(60)).is_passed(&tally, 100u64, false)
+        );
+        assert!(
+            !early(Decimal::permille(376), Decimal::percent(60)).is_passed(&tally, 100u64, false)
+        );
+        assert!(
+            !early(Decimal::permille(375), Decimal::percent(61)).is_passed(&tally, 100u64, false)
+        );
+
+        // once expired, passes if quorum <= 60% and threshold <= 75%
+        assert!(early(Decimal::percent(75), Decimal::percent(60)).is_passed(&tally, 100u64, true));
+        assert!(!early(Decimal::percent(76), Decimal::percent(60)).is_passed(&tally, 100u64, true));
+        assert!(!early(Decimal::percent(75), Decimal::percent(61)).is_passed(&tally, 100u64, true));
+    }
+
+    #[test]
+    fn rejected_threshold_quorum() {
+        let quorum = Threshold::ThresholdQuorum {
+            threshold: Decimal::percent(50),
+            quorum: Decimal::percent(40),
+        };
+        // early rejection assumes all missing votes are yes
+        let tally = votes(0, 50, 0, 0);
+        assert!(!quorum.is_rejected(&tally, 100u64, false));
+        let tally = votes(0, 40, 0, 11);
+        assert!(quorum.is_rejected(&tally, 100u64, false));
+        // abstain reduces the possible opinions: 50 against of 90
+        let tally = votes(0, 46, 10, 0);
+        assert!(quorum.is_rejected(&tally, 100u64, false));
+
+        // once expired, only cast votes count, regardless of quorum
+        let tally = votes(10, 12, 0, 0);
+        assert!(!quorum.is_rejected(&tally, 100u64, false));
+        assert!(quorum.is_rejected(&tally, 100u64, true));
+        // an even split rounds in favor of neither outcome
+        let tally = votes(10, 11, 0, 0);
+        assert!(!quorum.is_rejected(&tally, 100u64, true));
+        assert!(!quorum.is_passed(&tally, 100u64, true));
+
+        let tally = votes(0, 0, 0, 0);
+        assert!(!quorum.is_rejected(&tally, 100u64, true));
+    }
+
+    fn cosmos_gov() -> Threshold {
+        Threshold::ThresholdQuorumVeto {
+            threshold: Decimal::percent(50),
+            quorum: Decimal::permille(334),
+            veto_threshold: Decimal::permille(334),
+        }
+    }
+
+    #[test]
+    fn passed_threshold_quorum_veto() {
+        let gov = cosmos_gov();
+        // quorum is always required
+        let tally = votes(30, 0, 0, 0);
+        assert!(!gov.is_passed(&tally, 100u64, true));
+
+        // vetoes count as no, and can block a majority on their own
+        let tally = votes(30, 5, 0, 5);
+        assert!(gov.is_passed(&tally, 100u64, true));
+        let tally = votes(30, 0, 0, 14);
+        assert!(gov.is_passed(&tally, 100u64, true));
+        let tally = votes(30, 0, 0, 16);
+        assert!(!gov.is_passed(&tally, 100u64, true));
+        // abstain counts towards the votes cast, diluting the veto share
+        let tally = votes(30, 0, 10, 16);
+        assert!(gov.is_passed(&tally, 100u64, true));
+
+        // supermajority
+        let supermajority = Threshold::ThresholdQuorumVeto {
+            threshold: Decimal::permille(667),
+            quorum: Decimal::permille(334),
+            veto_threshold: Decimal::permille(334),
+        };
+        let tally = votes(60, 40, 0, 0);
+        assert!(gov.is_passed(&tally, 100u64, true));
+        assert!(!supermajority.is_passed(&tally, 100u64, true));
+        let tally = votes(67, 33, 0, 0);
+        assert!(supermajority.is_passed(&tally, 100u64, true));
+
+        // passes early only if the remaining weight cannot veto anymore
+        let tally = votes(60, 0, 0, 0);
+        assert!(!gov.is_passed(&tally, 100u64, false));
+        let tally = votes(67, 0, 0, 0);
+        assert!(gov.is_passed(&tally, 100u64, false));
+        let tally = votes(66, 0, 0, 1);
+        assert!(!gov.is_passed(&tally, 100u64, false));
+    }
+
+    #[test]
+    fn rejected_threshold_quorum_veto() {
+        let gov = cosmos_gov();
+        // exactly at the veto threshold is not vetoed
+        let tally = votes(600, 0, 66, 334);
+        assert!(!gov.is_rejected(&tally, 1000u64, true));
+        assert!(gov.is_passed(&tally, 1000u64, true));
+        let tally = votes(600, 0, 65, 335);
+        assert!(gov.is_rejected(&tally, 1000u64, true));
+        assert!(!gov.is_passed(&tally, 1000u64, true));
+
+        // before expiration, vetoes are measured against the total weight
+        let tally = votes(0, 0, 0, 33);
+        assert!(!gov.is_rejected(&tally, 100u64, false));
+        assert!(gov.is_rejected(&tally, 100u64, true));
+        let tally = votes(0, 0, 0, 35);
+        assert!(gov.is_rejected(&tally, 100u64, false));
+
+        // a majority against rejects as with ThresholdQuorum
+        let tally = votes(0, 51, 0, 0);
+        assert!(gov.is_rejected(&tally, 100u64, false));
+        let tally = votes(10, 12, 0, 0);
+        assert!(!gov.is_rejected(&tally, 100u64, false));
+        assert!(gov.is_rejected(&tally, 100u64, true));
+    }
+
+    #[test]
+    fn weights_deserialize_from_numbers_and_strings() {
+        // u64 based JSON as it was stored before
+        let threshold: Threshold = from_json(br#"{"absolute_count":{"weight":5}}"#).unwrap();
+        assert_eq!(
+            threshold,
+            Threshold::AbsoluteCount {
+                weight: Uint256::new(5)
+            }
+        );
+        let res: ThresholdResponse = from_json(
+            br#"{"threshold_quorum":{"threshold":"0.5","quorum":"0.3","total_weight":100}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            res,
+            ThresholdResponse::ThresholdQuorum {
+                threshold: Decimal::percent(50),
+                quorum: Decimal::percent(30),
+                total_weight: Uint256::new(100),
+            }
+        );
+
+        // Uint256 JSON, as it is serialized now
+        let threshold = Threshold::AbsoluteCount {
+            weight: Uint256::MAX,
+        };
+        let json = to_json_vec(&threshold).unwrap();
+        assert_eq!(
+            String::from_utf8(json.clone()).unwrap(),
+            format!(r#"{{"absolute_count":{{"weight":"{}"}}}}"#, Uint256::MAX)
+        );
+        assert_eq!(from_json::<Threshold>(&json).unwrap(), threshold);
+
+        let votes: Votes = from_json(br#"{"yes":1,"no":"2","abstain":3,"veto":"4"}"#).unwrap();
+        assert_eq!(votes.total(), Uint256::new(10));
+
+        // negative or fractional numbers are rejected
+        from_json::<Threshold>(br#"{"absolute_count":{"weight":-5}}"#).unwrap_err();
+        from_json::<Threshold>(br#"{"absolute_count":{"weight":1.5}}"#).unwrap_err();
+        from_json::<Threshold>(br#"{"absolute_count":{"weight":"abc"}}"#).unwrap_err();
+    }
+
+    #[test]
+    fn token_weighted_threshold() {
+        // total supply beyond u64, like 1 billion tokens with 18 decimals
+        let supply = Uint128::new(1_000_000_000) * Uint128::new(10u128.pow(18));
+        let threshold = Threshold::AbsolutePercentage {
+            percentage: Decimal::percent(50),
+        };
+        threshold.validate(supply).unwrap();
+        assert_eq!(
+            threshold.to_response(supply),
+            ThresholdResponse::AbsolutePercentage {
+                percentage: Decimal::percent(50),
+                total_weight: supply.into(),
+            }
+        );
+
+        let half = Uint256::from(supply) / Uint256::new(2);
+        let mut tally = Votes {
+            yes: half - Uint256::one(),
+            ..Votes::default()
+        };
+        assert!(!threshold.is_passed(&tally, supply, false));
+        tally.yes = half;
+        assert!(threshold.is_passed(&tally, supply, false));
+
+        let fixed = Threshold::AbsoluteCount {
+            weight: Uint256::from(supply) + Uint256::one(),
+        };
+        let err = fixed.validate(supply).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ThresholdError::UnreachableWeight {}.to_string()
+        );
+
+        // no overflow for the largest weights
+        assert_eq!(votes_needed(Uint256::MAX, &Decimal::one()), Uint256::MAX);
+        assert_eq!(
+            votes_needed(Uint256::MAX, &Decimal::percent(50)),
+            Uint256::MAX / Uint256::new(2) + Uint256::one()
+        );
     }
 }
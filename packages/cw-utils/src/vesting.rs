@@ -0,0 +1,375 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{BlockInfo, Decimal, Uint256};
+use thiserror::Error;
+
+use crate::Scheduled;
+
+/// A point of a piecewise-linear vesting curve
+#[cw_serde]
+pub struct VestingCheckpoint {
+    pub at: Scheduled,
+    /// Share of the total that is vested once `at` is reached
+    pub ratio: Decimal,
+}
+
+/// VestingSchedule describes how a total amount is released over time.
+/// All points of one schedule must be either height or time based.
+/// Partial amounts are always rounded down, so the full total is only vested at the end.
+#[cw_serde]
+pub enum VestingSchedule {
+    /// Nothing is vested before `at`, everything afterwards
+    Cliff { at: Scheduled },
+    /// Vests linearly from nothing at `start` to everything at `end`
+    Linear { start: Scheduled, end: Scheduled },
+    /// Vests linearly from `start` to `end`, but nothing can be released before `cliff`.
+    /// At the cliff, everything accrued since `start` is vested at once.
+    CliffLinear {
+        start: Scheduled,
+        cliff: Scheduled,
+        end: Scheduled,
+    },
+    /// Vests linearly between consecutive checkpoints. Nothing is vested before the
+    /// first one, which may have a non-zero ratio to express a cliff.
+    PiecewiseLinear { checkpoints: Vec<VestingCheckpoint> },
+}
+
+impl VestingSchedule {
+    /// returns error if the points of this schedule are not consistent
+    pub fn validate(&self) -> Result<(), VestingError> {
+        match self {
+            VestingSchedule::Cliff { .. } => Ok(()),
+            VestingSchedule::Linear { start, end } => ensure_before(start, end),
+            VestingSchedule::CliffLinear { start, cliff, end } => {
+                ensure_before(start, end)?;
+                ensure_not_after(start, cliff)?;
+                ensure_not_after(cliff, end)
+            }
+            VestingSchedule::PiecewiseLinear { checkpoints } => {
+                let last = checkpoints.last().ok_or(VestingError::NoCheckpoints {})?;
+                for pair in checkpoints.windows(2) {
+                    ensure_before(&pair[0].at, &pair[1].at)?;
+                    if pair[0].ratio > pair[1].ratio {
+                        return Err(VestingError::DecreasingRatio {});
+                    }
+                }
+                if last.ratio != Decimal::one() {
+                    return Err(VestingError::IncompleteVesting {});
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns how much of `total` is vested at the given block.
+    /// The schedule is expected to be valid, see `validate`.
+    pub fn vested_at(&self, block: &BlockInfo, total: Uint256) -> Uint256 {
+        match self {
+            VestingSchedule::Cliff { at } => {
+                if at.is_triggered(block) {
+                    total
+                } else {
+                    Uint256::zero()
+                }
+            }
+            VestingSchedule::Linear { start, end } => {
+                interpolate(block, start, end, Uint256::zero(), total)
+            }
+            VestingSchedule::CliffLinear { start, cliff, end } => {
+                if cliff.is_triggered(block) {
+                    interpolate(block, start, end, Uint256::zero(), total)
+                } else {
+                    Uint256::zero()
+                }
+            }
+            VestingSchedule::PiecewiseLinear { checkpoints } => {
+                // the last checkpoint that was reached
+                let reached = checkpoints.iter().rposition(|c| c.at.is_triggered(block));
+                match reached {
+                    None => Uint256::zero(),
+                    Some(i) => {
+                        let from = &checkpoints[i];
+                        let from_amount = total.mul_floor(from.ratio);
+                        match checkpoints.get(i + 1) {
+                            Some(to) => interpolate(
+                                block,
+                                &from.at,
+                                &to.at,
+                                from_amount,
+                                total.mul_floor(to.ratio),
+                            ),
+                            None => from_amount,
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Linear interpolation between `from_amount` at `start` and `to_amount` at `end`
+fn interpolate(
+    block: &BlockInfo,
+    start: &Scheduled,
+    end: &Scheduled,
+    from_amount: Uint256,
+    to_amount: Uint256,
+) -> Uint256 {
+    if end.is_triggered(block) {
+        return to_amount;
+    }
+    if !start.is_triggered(block) {
+        return from_amount;
+    }
+    let (elapsed, length) = match (start, end) {
+        (Scheduled::AtHeight(s), Scheduled::AtHeight(e)) => (block.height - s, e - s),
+        (Scheduled::AtTime(s), Scheduled::AtTime(e)) => {
+            (block.time.nanos() - s.nanos(), e.nanos() - s.nanos())
+        }
+        // invalid schedule, nothing vests before the end
+        _ => return from_amount,
+    };
+    from_amount + (to_amount - from_amount).multiply_ratio(elapsed, length)
+}
+
+/// Asserts that a < b, with both of the same kind
+fn ensure_before(a: &Scheduled, b: &Scheduled) -> Result<(), VestingError> {
+    match a.partial_cmp(b) {
+        None => Err(VestingError::MixedUnits {}),
+        Some(std::cmp::Ordering::Less) => Ok(()),
+        _ => Err(VestingError::InvalidOrder {}),
+    }
+}
+
+/// Asserts that a <= b, with both of the same kind
+fn ensure_not_after(a: &Scheduled, b: &Scheduled) -> Result<(), VestingError> {
+    match a.partial_cmp(b) {
+        None => Err(VestingError::MixedUnits {}),
+        Some(std::cmp::Ordering::Greater) => Err(VestingError::InvalidOrder {}),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum VestingError {
+    #[error("Cannot mix height and time in a vesting schedule")]
+    MixedUnits {},
+
+    #[error("Vesting points must be in increasing order")]
+    InvalidOrder {},
+
+    #[error("Piecewise vesting requires at least one checkpoint")]
+    NoCheckpoints {},
+
+    #[error("Vested ratio cannot decrease between checkpoints")]
+    DecreasingRatio {},
+
+    #[error("Last checkpoint must vest the full amount")]
+    IncompleteVesting {},
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::Timestamp;
+
+    fn at_height(height: u64) -> BlockInfo {
+        BlockInfo {
+            height,
+            time: Timestamp::from_seconds(1_000_000),
+            chain_id: "foo".to_string(),
+        }
+    }
+
+    fn at_time(seconds: u64) -> BlockInfo {
+        BlockInfo {
+            height: 12345,
+            time: Timestamp::from_seconds(seconds),
+            chain_id: "foo".to_string(),
+        }
+    }
+
+    fn time(seconds: u64) -> Scheduled {
+        Scheduled::AtTime(Timestamp::from_seconds(seconds))
+    }
+
+    fn checkpoint(height: u64, percent: u64) -> VestingCheckpoint {
+        VestingCheckpoint {
+            at: Scheduled::AtHeight(height),
+            ratio: Decimal::percent(percent),
+        }
+    }
+
+    const TOTAL: Uint256 = Uint256::new(1000);
+
+    #[test]
+    fn cliff_vesting() {
+        let schedule = VestingSchedule::Cliff {
+            at: Scheduled::AtHeight(100),
+        };
+        schedule.validate().unwrap();
+        assert_eq!(schedule.vested_at(&at_height(99), TOTAL), Uint256::zero());
+        assert_eq!(schedule.vested_at(&at_height(100), TOTAL), TOTAL);
+        assert_eq!(schedule.vested_at(&at_height(5000), TOTAL), TOTAL);
+    }
+
+    #[test]
+    fn linear_vesting() {
+        let schedule = VestingSchedule::Linear {
+            start: time(1000),
+            end: time(4000),
+        };
+        schedule.validate().unwrap();
+        assert_eq!(schedule.vested_at(&at_time(0), TOTAL), Uint256::zero());
+        assert_eq!(schedule.vested_at(&at_time(1000), TOTAL), Uint256::zero());
+        assert_eq!(schedule.vested_at(&at_time(2500), TOTAL), Uint256::new(500));
+        // rounds down
+        assert_eq!(schedule.vested_at(&at_time(2000), TOTAL), Uint256::new(333));
+        assert_eq!(schedule.vested_at(&at_time(4000), TOTAL), TOTAL);
+        assert_eq!(schedule.vested_at(&at_time(9000), TOTAL), TOTAL);
+    }
+
+    #[test]
+    fn cliff_linear_vesting() {
+        let schedule = VestingSchedule::CliffLinear {
+            start: Scheduled::AtHeight(100),
+            cliff: Scheduled::AtHeight(150),
+            end: Scheduled::AtHeight(200),
+        };
+        schedule.validate().unwrap();
+        assert_eq!(schedule.vested_at(&at_height(120), TOTAL), Uint256::zero());
+        assert_eq!(schedule.vested_at(&at_height(149), TOTAL), Uint256::zero());
+        // accrued amount is released at the cliff
+        assert_eq!(
+            schedule.vested_at(&at_height(150), TOTAL),
+            Uint256::new(500)
+        );
+        assert_eq!(
+            schedule.vested_at(&at_height(175), TOTAL),
+            Uint256::new(750)
+        );
+        assert_eq!(schedule.vested_at(&at_height(200), TOTAL), TOTAL);
+    }
+
+    #[test]
+    fn piecewise_linear_vesting() {
+        let schedule = VestingSchedule::PiecewiseLinear {
+            checkpoints: vec![
+                checkpoint(100, 10),
+                checkpoint(200, 50),
+                checkpoint(300, 50),
+                checkpoint(400, 100),
+            ],
+        };
+        schedule.validate().unwrap();
+        let vested = |height| schedule.vested_at(&at_height(height), TOTAL);
+        assert_eq!(vested(99), Uint256::zero());
+        assert_eq!(vested(100), Uint256::new(100));
+        assert_eq!(vested(150), Uint256::new(300));
+        assert_eq!(vested(200), Uint256::new(500));
+        assert_eq!(vested(250), Uint256::new(500));
+        assert_eq!(vested(350), Uint256::new(750));
+        assert_eq!(vested(400), TOTAL);
+        assert_eq!(vested(401), TOTAL);
+    }
+
+    #[test]
+    fn validate_rejects_mixed_units() {
+        let err = VestingSchedule::Linear {
+            start: Scheduled::AtHeight(100),
+            end: time(200),
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(err, VestingError::MixedUnits {});
+
+        let err = VestingSchedule::CliffLinear {
+            start: time(100),
+            cliff: Scheduled::AtHeight(150),
+            end: time(200),
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(err, VestingError::MixedUnits {});
+
+        let err = VestingSchedule::PiecewiseLinear {
+            checkpoints: vec![
+                checkpoint(100, 10),
+                VestingCheckpoint {
+                    at: time(200),
+                    ratio: Decimal::one(),
+                },
+            ],
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(err, VestingError::MixedUnits {});
+    }
+
+    #[test]
+    fn validate_rejects_invalid_points() {
+        let err = VestingSchedule::Linear {
+            start: time(200),
+            end: time(200),
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(err, VestingError::InvalidOrder {});
+
+        let err = VestingSchedule::CliffLinear {
+            start: time(100),
+            cliff: time(250),
+            end: time(200),
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(err, VestingError::InvalidOrder {});
+
+        // cliff may be at the start or the end
+        VestingSchedule::CliffLinear {
+            start: time(100),
+            cliff: time(100),
+            end: time(200),
+        }
+        .validate()
+        .unwrap();
+        VestingSchedule::CliffLinear {
+            start: time(100),
+            cliff: time(200),
+            end: time(200),
+        }
+        .validate()
+        .unwrap();
+
+        let err = VestingSchedule::PiecewiseLinear {
+            checkpoints: vec![],
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(err, VestingError::NoCheckpoints {});
+
+        let err = VestingSchedule::PiecewiseLinear {
+            checkpoints: vec![checkpoint(200, 10), checkpoint(100, 100)],
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(err, VestingError::InvalidOrder {});
+
+        let err = VestingSchedule::PiecewiseLinear {
+            checkpoints: vec![
+                checkpoint(100, 60),
+                checkpoint(200, 40),
+                checkpoint(300, 100),
+            ],
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(err, VestingError::DecreasingRatio {});
+
+        let err = VestingSchedule::PiecewiseLinear {
+            checkpoints: vec![checkpoint(100, 60), checkpoint(200, 90)],
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(err, VestingError::IncompleteVesting {});
+    }
+}